@@ -0,0 +1,12 @@
+#version 140
+in vec2 position;
+in vec2 tex_coords;
+in vec4 color;
+out vec2 v_tex_coords;
+out vec4 v_color;
+uniform vec2 u_screen;
+void main() {
+    v_tex_coords = tex_coords;
+    v_color = color;
+    gl_Position = vec4(position.x / u_screen.x * 2.0 - 1.0, 1.0 - position.y / u_screen.y * 2.0, 0.0, 1.0);
+}
@@ -0,0 +1,410 @@
+use cgmath::{
+    InnerSpace,
+    Quaternion,
+    Vector3
+};
+use rand::Rng;
+
+use crate::{
+    avoidance::{
+        avoid,
+        clamp_to_nav_mesh
+    },
+    entity::Entity,
+    obstacle::Footprint,
+    path::PathSettings,
+    physics::PhysicsSettings,
+    shape::Shape,
+    steering::{
+        Behaviour,
+        Steering,
+        SteeringAgent,
+        SteeringLimits
+    },
+    transform::Transform
+};
+use navmesh::{
+    NavQuery,
+    NavPathMode,
+    NavVec3,
+    NavMesh
+};
+
+// distance from the last waypoint counted as arriving
+const ARRIVAL_DISTANCE: f32 = 0.05;
+// distance a moving agent has to cover within the stuck time
+const STUCK_PROGRESS: f32 = 0.05;
+const STUCK_TIME: f32 = 2.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NavStatus {
+    Idle,
+    Moving,
+    Arrived,
+    Unreachable,
+    Stuck
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NavEvent {
+    WaypointReached([f32; 3]),
+    Arrived([f32; 3]),
+    Unreachable([f32; 3]),
+    Stuck([f32; 3])
+}
+
+/// How a steered agent turns, always about the up axis so it stays upright
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Facing {
+    Fixed,
+    Velocity,
+    Target([f32; 3])
+}
+
+/// Navigation agent component, pathing and steering the transform of its entity, turning to face
+/// where it goes and reporting arrival, unreachable targets and getting stuck
+#[derive(Debug,Clone)]
+pub struct NavAgent {
+    steering: Steering,
+    velocity: Vector3<f32>,
+    radius: f32,
+    facing: Facing,
+    forward: Vector3<f32>,
+    max_angular_speed: f32,
+    nav_status: NavStatus,
+    nav_events: Vec<NavEvent>,
+    destination: Option<NavVec3>,
+    path_settings: PathSettings,
+    progress_position: Vector3<f32>,
+    stuck_time: f32
+}
+#[allow(dead_code)]
+impl NavAgent {
+    /// Constructs an idle agent following the path to its waypoints
+    /// takes   velocity as [f32; 3]
+    /// returns agent as NavAgent
+    pub fn new(velocity: [f32; 3]) -> NavAgent {
+        let mut steering = Steering::new(SteeringLimits::new(1.0, 1.0, std::f32::consts::PI));
+        steering.add_behaviour(Behaviour::FollowPath { waypoint_radius: 0.1, slowing_radius: 1.0 }, 1.0);
+        NavAgent {
+            steering,
+            velocity: velocity.into(),
+            radius: 0.25,
+            facing: Facing::Fixed,
+            forward: Vector3::unit_x(),
+            max_angular_speed: std::f32::consts::PI,
+            nav_status: NavStatus::Idle,
+            nav_events: Vec::new(),
+            destination: None,
+            path_settings: PathSettings::funnel(0.1),
+            progress_position: Vector3::new(0.0, 0.0, 0.0),
+            stuck_time: 0.0
+        }
+    }
+    /// Turns the agent as it is steered
+    /// takes   facing mode as Facing
+    ///         shape of the entity's mesh, giving its forward axis, as Shape
+    ///         placement of the mesh relative to the entity as Transform
+    ///         maximum angular speed in radians per second as f32
+    pub fn set_facing(&mut self, facing: Facing, shape: &Shape, shape_transform: &Transform, max_angular_speed: f32) {
+        let model = shape_transform.to_model_matrix();
+        self.facing = facing;
+        self.forward = (model * Vector3::from(shape.get_forward()).extend(0.0)).truncate().normalize();
+        self.max_angular_speed = max_angular_speed;
+    }
+    /// Changes the facing mode, keeping the forward axis and angular speed
+    /// takes   facing mode as Facing
+    pub fn set_facing_mode(&mut self, facing: Facing) {
+        self.facing = facing;
+    }
+    pub fn get_facing(&self) -> Facing {
+        self.facing
+    }
+    pub fn get_steering(&self) -> &Steering {
+        &self.steering
+    }
+    pub fn get_steering_mut(&mut self) -> &mut Steering {
+        &mut self.steering
+    }
+    pub fn get_velocity(&self) -> [f32; 3] {
+        self.velocity.into()
+    }
+    pub fn get_radius(&self) -> f32 {
+        self.radius
+    }
+    pub fn set_radius(&mut self, radius: f32) {
+        self.radius = radius;
+    }
+    /// Describes the agent to the steering of others
+    /// takes   the agent's entity as Entity
+    ///         transform of the agent's entity as Transform
+    /// returns entity, position, velocity and radius as SteeringAgent
+    pub fn get_steering_agent(&self, entity: Entity, transform: &Transform) -> SteeringAgent {
+        SteeringAgent {
+            entity,
+            position: transform.get_cgmath_position(),
+            velocity: self.velocity,
+            radius: self.radius,
+            avoiding: self.steering.get_avoidance().is_some()
+        }
+    }
+
+    pub fn get_nav_status(&self) -> NavStatus {
+        self.nav_status
+    }
+    /// Takes the navigation events raised since the last call
+    /// returns events in the order they happened as Vec<NavEvent>
+    pub fn take_nav_events(&mut self) -> Vec<NavEvent> {
+        self.nav_events.drain(..).collect()
+    }
+    /// Sets how paths to waypoints are straightened and smoothed, from the next waypoint on
+    /// takes   path settings as PathSettings
+    pub fn set_path_settings(&mut self, path_settings: PathSettings) {
+        self.path_settings = path_settings;
+    }
+    pub fn get_path_settings(&self) -> PathSettings {
+        self.path_settings
+    }
+    /// Paths the agent to a point, leaving it unreachable when the navigation mesh has no path
+    /// takes   navigation mesh as NavMesh
+    ///         transform of the agent's entity as Transform
+    ///         point to reach as NavVec3
+    pub fn set_waypoint(&mut self, navmesh: &NavMesh, transform: &Transform, waypoint: NavVec3) {
+        let path = navmesh.find_path(
+            transform.get_nav_position(),
+            waypoint,
+            NavQuery::Accuracy,
+            NavPathMode::MidPoints
+        );
+        self.progress_position = transform.get_cgmath_position();
+        self.stuck_time = 0.0;
+        match path {
+            Some(path) if !path.is_empty() => {
+                let corners = self.path_settings.corners(navmesh, &path);
+                let curve = self.path_settings.curve(&corners);
+                self.steering.set_path(corners);
+                if let Some(curve) = curve {
+                    self.steering.set_curve(curve);
+                }
+                self.nav_status = NavStatus::Moving;
+                self.destination = Some(waypoint);
+            },
+            _ => {
+                self.steering.clear_path();
+                self.destination = None;
+                self.nav_status = NavStatus::Unreachable;
+                self.nav_events.push(NavEvent::Unreachable([waypoint.x, waypoint.y, waypoint.z]));
+            }
+        }
+    }
+    /// Tests whether the rest of the agent's path runs through any of the footprints
+    /// takes   transform of the agent's entity as Transform
+    ///         footprints as [Footprint]
+    /// returns whether a footprint blocks the path as bool
+    pub fn path_crosses(&self, transform: &Transform, footprints: &[Footprint]) -> bool {
+        if self.destination.is_none() {
+            return false;
+        }
+        let mut points = vec![transform.get_cgmath_position()];
+        points.extend(self.steering.get_path().into_iter().map(Vector3::from));
+        points.windows(2).any(|segment| footprints.iter().any(|footprint| footprint.crosses(segment[0], segment[1])))
+    }
+    /// Paths the agent again to where it was heading, if it still has somewhere to go
+    /// takes   navigation mesh as NavMesh
+    ///         transform of the agent's entity as Transform
+    pub fn replan(&mut self, nav_mesh: &NavMesh, transform: &Transform) {
+        if let Some(destination) = self.destination {
+            self.set_waypoint(nav_mesh, transform, destination);
+        }
+    }
+    /// Holds off the stuck check while the agent waits its turn to plan again
+    /// takes   transform of the agent's entity as Transform
+    pub fn wait_for_replan(&mut self, transform: &Transform) {
+        self.progress_position = transform.get_cgmath_position();
+        self.stuck_time = 0.0;
+    }
+    /// Moves the agent's entity by a time step of steering, avoiding other agents when enabled and
+    /// keeping to the navigation mesh
+    /// takes   transform of the agent's entity as Transform
+    ///         navigation mesh as NavMesh
+    ///         physics settings as PhysicsSettings
+    ///         every agent as seen at the start of the tick as [SteeringAgent]
+    ///         index of this agent among the agents as usize
+    ///         random number generator as Rng
+    ///         time step in seconds as f32
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_time_step<R: Rng>(&mut self, transform: &mut Transform, nav_mesh: &NavMesh, physics: &PhysicsSettings,
+            agents: &[SteeringAgent], index: usize, rng: &mut R, time_step: f32) {
+        let acceleration = self.steering.calculate(agents, index, rng);
+        let mut velocity = self.steering.apply(self.velocity, acceleration, time_step);
+        if let Some(avoidance) = self.steering.get_avoidance() {
+            velocity = avoid(agents, index, velocity, self.steering.get_limits().get_max_speed(), &avoidance, time_step);
+        }
+        self.velocity = clamp_to_nav_mesh(nav_mesh, transform.get_cgmath_position(), velocity, time_step);
+        let step = self.velocity * time_step;
+        transform.position_by(step.into());
+        self.turn(transform, physics, time_step);
+        self.update_nav_status(transform.get_cgmath_position(), time_step);
+    }
+    // raises waypoint and arrival events, and watches for an agent on a path no longer getting anywhere
+    fn update_nav_status(&mut self, position: Vector3<f32>, time_step: f32) {
+        for waypoint in self.steering.take_reached() {
+            self.nav_events.push(NavEvent::WaypointReached(waypoint));
+        }
+        if self.nav_status != NavStatus::Moving && self.nav_status != NavStatus::Stuck {
+            return;
+        }
+        let path: Vec<Vector3<f32>> = self.steering.get_path().into_iter().map(Vector3::from).collect();
+        let goal = match path.last() {
+            Some(goal) => *goal,
+            None => return
+        };
+        if path.len() == 1 && (goal - position).magnitude() <= ARRIVAL_DISTANCE {
+            self.steering.clear_path();
+            self.destination = None;
+            self.nav_status = NavStatus::Arrived;
+            self.nav_events.push(NavEvent::WaypointReached(goal.into()));
+            self.nav_events.push(NavEvent::Arrived(goal.into()));
+            return;
+        }
+        if (position - self.progress_position).magnitude() > STUCK_PROGRESS {
+            self.progress_position = position;
+            self.stuck_time = 0.0;
+            self.nav_status = NavStatus::Moving;
+        } else {
+            self.stuck_time += time_step;
+            if self.stuck_time >= STUCK_TIME && self.nav_status == NavStatus::Moving {
+                self.nav_status = NavStatus::Stuck;
+                self.nav_events.push(NavEvent::Stuck(position.into()));
+            }
+        }
+    }
+    // slerps the forward axis towards the facing direction, both flattened onto the ground
+    fn turn(&mut self, transform: &mut Transform, physics: &PhysicsSettings, time_step: f32) {
+        let direction = match self.facing {
+            Facing::Fixed => return,
+            Facing::Velocity => self.velocity,
+            Facing::Target(target) => Vector3::from(target) - transform.get_cgmath_position()
+        };
+        let up = -Vector3::from(physics.get_gravity()).normalize();
+        let forward = self.forward - up * up.dot(self.forward);
+        let direction = direction - up * up.dot(direction);
+        if forward.magnitude2() < f32::EPSILON || direction.magnitude2() < f32::EPSILON {
+            return;
+        }
+        let orientation = Quaternion::from_arc(forward.normalize(), direction.normalize(), Some(up));
+        transform.orientation_towards(orientation.into(), self.max_angular_speed * time_step);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{
+        rngs::StdRng,
+        SeedableRng
+    };
+
+    const TIME_STEP: f32 = 0.05;
+
+    fn square(half_extent: f32, centre: [f32; 2]) -> (Vec<NavVec3>, Vec<navmesh::NavTriangle>) {
+        let vertices = [[1.0, 1.0], [-1.0, 1.0], [1.0, -1.0], [-1.0, -1.0]].iter()
+            .map(|corner| NavVec3::new(centre[0] + corner[0] * half_extent, centre[1] + corner[1] * half_extent, 0.0))
+            .collect();
+        (vertices, vec![(0, 1, 2).into(), (1, 2, 3).into()])
+    }
+
+    fn step(agent: &mut NavAgent, transform: &mut Transform, nav_mesh: &NavMesh, physics: &PhysicsSettings, rng: &mut StdRng) {
+        let agents = [agent.get_steering_agent(Entity::new(0), transform)];
+        agent.update_time_step(transform, nav_mesh, physics, &agents, 0, rng, TIME_STEP);
+    }
+
+    #[test]
+    fn facing_velocity_turns_upright_within_the_angular_speed() {
+        let (vertices, triangles) = square(2.0, [0.0, 0.0]);
+        let nav_mesh = NavMesh::new(vertices, triangles).unwrap();
+        let physics = PhysicsSettings::table([2.0, 2.0]);
+        let (mut agent, mut transform) = (NavAgent::new([0.0, 0.0, 0.0]), Transform::identity());
+        agent.set_facing_mode(Facing::Velocity);
+        agent.set_waypoint(&nav_mesh, &transform, NavVec3::new(0.0, 1.5, 0.0));
+        let mut rng = StdRng::seed_from_u64(0);
+        let up = -Vector3::from(physics.get_gravity()).normalize();
+        for _ in 0..40 {
+            let before = transform.get_cgmath_orientation();
+            step(&mut agent, &mut transform, &nav_mesh, &physics, &mut rng);
+            let after = transform.get_cgmath_orientation();
+            let turned = 2.0 * before.dot(after).abs().min(1.0).acos();
+            assert!(turned <= std::f32::consts::PI * TIME_STEP + 1.0e-4, "turned {} in a tick", turned);
+            let agent_up = Vector3::from(transform.transform_vector(up.into()));
+            assert!((agent_up - up).magnitude() < 1.0e-4, "tipped to {:?}", agent_up);
+        }
+        // by now the agent has swung round to face along its path
+        let forward = Vector3::from(transform.transform_vector([1.0, 0.0, 0.0]));
+        assert!(forward.dot(Vector3::unit_y()) > 0.99, "facing {:?}", forward);
+    }
+
+    #[test]
+    fn walking_to_a_waypoint_ends_arrived() {
+        let (vertices, triangles) = square(2.0, [0.0, 0.0]);
+        let nav_mesh = NavMesh::new(vertices, triangles).unwrap();
+        let physics = PhysicsSettings::table([2.0, 2.0]);
+        let (mut agent, mut transform) = (NavAgent::new([0.0, 0.0, 0.0]), Transform::identity());
+        assert_eq!(agent.get_nav_status(), NavStatus::Idle);
+        agent.set_waypoint(&nav_mesh, &transform, NavVec3::new(1.0, 1.5, 0.0));
+        assert_eq!(agent.get_nav_status(), NavStatus::Moving);
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut events = Vec::new();
+        for _ in 0..200 {
+            step(&mut agent, &mut transform, &nav_mesh, &physics, &mut rng);
+            events.append(&mut agent.take_nav_events());
+        }
+        assert_eq!(agent.get_nav_status(), NavStatus::Arrived);
+        match events.as_slice() {
+            [.., NavEvent::WaypointReached(reached), NavEvent::Arrived(goal)] => {
+                assert_eq!(reached, goal);
+                assert!((Vector3::from(*goal) - Vector3::new(1.0, 1.5, 0.0)).magnitude() < 1.0e-3);
+            },
+            _ => panic!("unexpected events {:?}", events)
+        }
+        assert!(agent.take_nav_events().is_empty());
+    }
+
+    #[test]
+    fn a_waypoint_on_another_island_is_unreachable() {
+        let (mut vertices, mut triangles) = square(1.0, [-2.0, 0.0]);
+        let (island, island_triangles) = square(1.0, [2.0, 0.0]);
+        vertices.extend(island);
+        triangles.extend(island_triangles.iter().map(|triangle| navmesh::NavTriangle { first: triangle.first + 4, second: triangle.second + 4, third: triangle.third + 4 }));
+        let nav_mesh = NavMesh::new(vertices, triangles).unwrap();
+        let mut agent = NavAgent::new([0.0, 0.0, 0.0]);
+        let transform = Transform::new([-2.0, 0.0, 0.0], [1.0, 0.0, 0.0, 0.0], [1.0, 1.0, 1.0]);
+        agent.set_waypoint(&nav_mesh, &transform, NavVec3::new(2.0, 0.0, 0.0));
+        assert_eq!(agent.get_nav_status(), NavStatus::Unreachable);
+        assert_eq!(agent.take_nav_events(), vec![NavEvent::Unreachable([2.0, 0.0, 0.0])]);
+        assert!(agent.get_steering().get_path().is_empty());
+    }
+
+    #[test]
+    fn an_agent_that_stops_getting_anywhere_is_stuck_once() {
+        let (vertices, triangles) = square(2.0, [0.0, 0.0]);
+        let nav_mesh = NavMesh::new(vertices, triangles).unwrap();
+        let physics = PhysicsSettings::table([2.0, 2.0]);
+        let (mut agent, mut transform) = (NavAgent::new([0.0, 0.0, 0.0]), Transform::identity());
+        agent.get_steering_mut().set_limits(SteeringLimits::new(0.01, 1.0, std::f32::consts::PI));
+        agent.set_waypoint(&nav_mesh, &transform, NavVec3::new(1.0, 1.5, 0.0));
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut events = Vec::new();
+        let ticks = (STUCK_TIME / TIME_STEP) as usize;
+        for _ in 0..ticks - 2 {
+            step(&mut agent, &mut transform, &nav_mesh, &physics, &mut rng);
+        }
+        assert_eq!(agent.get_nav_status(), NavStatus::Moving);
+        for _ in 0..ticks {
+            step(&mut agent, &mut transform, &nav_mesh, &physics, &mut rng);
+            events.append(&mut agent.take_nav_events());
+        }
+        assert_eq!(agent.get_nav_status(), NavStatus::Stuck);
+        assert_eq!(events.iter().filter(|event| matches!(event, NavEvent::Stuck(_))).count(), 1);
+    }
+}
@@ -0,0 +1,148 @@
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AtlasPlacement {
+    pub page: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32
+}
+impl AtlasPlacement {
+    /// Constructs the texture coordinate transform of a placement
+    /// takes   page size in pixels as u32
+    /// returns uv offset and scale for a bottom up texture as [f32; 4]
+    pub fn get_uv_transform(&self, page_size: u32) -> [f32; 4] {
+        let size = page_size as f32;
+        [
+            self.x as f32 / size,
+            1.0 - (self.y + self.height) as f32 / size,
+            self.width as f32 / size,
+            self.height as f32 / size
+        ]
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AtlasShelf {
+    y: u32,
+    height: u32,
+    width_used: u32
+}
+
+#[derive(Debug, Clone)]
+pub struct AtlasPage {
+    pixels: Vec<u8>,
+    shelves: Vec<AtlasShelf>,
+    area_used: u64
+}
+
+#[derive(Debug, Clone)]
+pub struct AtlasBuilder {
+    page_size: u32,
+    padding: u32,
+    pages: Vec<AtlasPage>
+}
+#[allow(dead_code)]
+impl AtlasBuilder {
+    pub fn new(page_size: u32, padding: u32) -> AtlasBuilder {
+        AtlasBuilder {
+            page_size,
+            padding,
+            pages: Vec::new()
+        }
+    }
+    pub fn get_page_size(&self) -> u32 {
+        self.page_size
+    }
+    pub fn get_page_count(&self) -> usize {
+        self.pages.len()
+    }
+    /// Checks if an image can be placed on an empty page
+    /// takes   image width and height as u32
+    /// returns whether the padded image fits as bool
+    pub fn fits(&self, width: u32, height: u32) -> bool {
+        width + self.padding * 2 <= self.page_size && height + self.padding * 2 <= self.page_size
+    }
+    /// Reserves room on the first page with a shelf the image fits, opening pages as needed
+    /// takes   image width and height as u32
+    /// returns placement of the image excluding its padding as Option<AtlasPlacement>
+    pub fn reserve(&mut self, width: u32, height: u32) -> Option<AtlasPlacement> {
+        if !self.fits(width, height) {
+            return None;
+        }
+        let padded_width = width + self.padding * 2;
+        let padded_height = height + self.padding * 2;
+        for (index, page) in self.pages.iter_mut().enumerate() {
+            if let Some((x, y)) = AtlasBuilder::reserve_on_page(page, self.page_size, padded_width, padded_height) {
+                page.area_used += width as u64 * height as u64;
+                return Some(AtlasPlacement { page: index, x: x + self.padding, y: y + self.padding, width, height });
+            }
+        }
+        let mut page = AtlasPage {
+            pixels: vec![0u8; (self.page_size * self.page_size * 4) as usize],
+            shelves: Vec::new(),
+            area_used: 0
+        };
+        let (x, y) = AtlasBuilder::reserve_on_page(&mut page, self.page_size, padded_width, padded_height)?;
+        page.area_used += width as u64 * height as u64;
+        self.pages.push(page);
+        Some(AtlasPlacement { page: self.pages.len() - 1, x: x + self.padding, y: y + self.padding, width, height })
+    }
+    fn reserve_on_page(page: &mut AtlasPage, page_size: u32, width: u32, height: u32) -> Option<(u32, u32)> {
+        // best fitting shelf keeps tall images from wasting short shelves
+        let mut best: Option<usize> = None;
+        for (index, shelf) in page.shelves.iter().enumerate() {
+            if shelf.height >= height && shelf.width_used + width <= page_size
+                    && best.is_none_or(|best| shelf.height < page.shelves[best].height) {
+                best = Some(index);
+            }
+        }
+        if let Some(index) = best {
+            let shelf = &mut page.shelves[index];
+            let x = shelf.width_used;
+            shelf.width_used += width;
+            return Some((x, shelf.y));
+        }
+        let top = page.shelves.last().map_or(0, |shelf| shelf.y + shelf.height);
+        if top + height > page_size {
+            return None;
+        }
+        page.shelves.push(AtlasShelf { y: top, height, width_used: width });
+        Some((0, top))
+    }
+    /// Copies rgba pixels into a reserved placement, extending the edges into the padding
+    /// takes   placement as AtlasPlacement
+    ///         top down rgba pixels as [u8]
+    pub fn write(&mut self, placement: &AtlasPlacement, pixels: &[u8]) {
+        let page_size = self.page_size as i64;
+        let padding = self.padding as i64;
+        let page = &mut self.pages[placement.page];
+        let width = placement.width as i64;
+        let height = placement.height as i64;
+        for y in -padding..height + padding {
+            let source_y = y.max(0).min(height - 1);
+            let target_y = placement.y as i64 + y;
+            for x in -padding..width + padding {
+                let source_x = x.max(0).min(width - 1);
+                let target_x = placement.x as i64 + x;
+                let source = ((source_y * width + source_x) * 4) as usize;
+                let target = ((target_y * page_size + target_x) * 4) as usize;
+                page.pixels[target..target + 4].copy_from_slice(&pixels[source..source + 4]);
+            }
+        }
+    }
+    /// Measures how much of each page holds image data
+    /// returns fraction of each page covered as Vec<f32>
+    pub fn get_utilisation(&self) -> Vec<f32> {
+        let area = self.page_size as f32 * self.page_size as f32;
+        self.pages.iter()
+            .map(|page| page.area_used as f32 / area)
+            .collect()
+    }
+    /// Takes the finished page images
+    /// returns top down rgba pixels of each page as Vec<Vec<u8>>
+    pub fn into_pages(self) -> Vec<Vec<u8>> {
+        self.pages.into_iter()
+            .map(|page| page.pixels)
+            .collect()
+    }
+}
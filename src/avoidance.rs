@@ -0,0 +1,283 @@
+use cgmath::{
+    InnerSpace,
+    Vector2,
+    Vector3
+};
+use navmesh::{
+    NavMesh,
+    NavQuery,
+    NavVec3
+};
+
+use crate::steering::SteeringAgent;
+
+const EPSILON: f32 = 1.0e-5;
+
+/// Settings of reciprocal velocity obstacle avoidance, agents only react to neighbours within
+/// the neighbour distance and to collisions expected within the time horizon
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Avoidance {
+    time_horizon: f32,
+    neighbour_distance: f32
+}
+#[allow(dead_code)]
+impl Avoidance {
+    pub fn new(time_horizon: f32, neighbour_distance: f32) -> Avoidance {
+        Avoidance {
+            time_horizon,
+            neighbour_distance
+        }
+    }
+    pub fn get_time_horizon(&self) -> f32 {
+        self.time_horizon
+    }
+    pub fn get_neighbour_distance(&self) -> f32 {
+        self.neighbour_distance
+    }
+}
+
+/// Velocities on the left of the line through point along direction are allowed
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Line {
+    point: Vector2<f32>,
+    direction: Vector2<f32>
+}
+
+/// Picks the velocity closest to the preferred one that avoids every neighbour for the time horizon,
+/// optimal reciprocal collision avoidance in the xy plane of the table, the z component passes through
+/// takes   every agent, the avoiding one included, as [SteeringAgent]
+///         index of the avoiding agent as usize
+///         preferred velocity as Vector3<f32>
+///         maximum speed as f32
+///         avoidance settings as Avoidance
+///         time step in seconds as f32
+/// returns avoiding velocity as Vector3<f32>
+pub fn avoid(agents: &[SteeringAgent], index: usize, preferred: Vector3<f32>, max_speed: f32, avoidance: &Avoidance,
+        time_step: f32) -> Vector3<f32> {
+    let agent = agents[index];
+    let position = agent.position.truncate();
+    let velocity = agent.velocity.truncate();
+    let mut lines = Vec::new();
+    for (other_index, other) in agents.iter().enumerate() {
+        let relative_position = other.position.truncate() - position;
+        if other_index == index || relative_position.magnitude() > avoidance.neighbour_distance {
+            continue;
+        }
+        let relative_velocity = velocity - other.velocity.truncate();
+        let distance_squared = relative_position.magnitude2();
+        let combined_radius = agent.radius + other.radius;
+        let combined_radius_squared = combined_radius * combined_radius;
+        let (direction, u) = if distance_squared > combined_radius_squared {
+            // w points from the centre of the truncated cone's cap to the relative velocity
+            let w = relative_velocity - relative_position / avoidance.time_horizon;
+            let w_length_squared = w.magnitude2();
+            let dot = w.dot(relative_position);
+            if dot < 0.0 && dot * dot > combined_radius_squared * w_length_squared {
+                // project on the cap
+                let w_length = w_length_squared.sqrt();
+                let unit_w = w / w_length;
+                (Vector2::new(unit_w.y, -unit_w.x), unit_w * (combined_radius / avoidance.time_horizon - w_length))
+            } else {
+                // project on the nearer leg
+                let leg = (distance_squared - combined_radius_squared).sqrt();
+                let direction = if determinant(relative_position, w) > 0.0 {
+                    Vector2::new(
+                        relative_position.x * leg - relative_position.y * combined_radius,
+                        relative_position.x * combined_radius + relative_position.y * leg
+                    ) / distance_squared
+                } else {
+                    -Vector2::new(
+                        relative_position.x * leg + relative_position.y * combined_radius,
+                        -relative_position.x * combined_radius + relative_position.y * leg
+                    ) / distance_squared
+                };
+                (direction, direction * relative_velocity.dot(direction) - relative_velocity)
+            }
+        } else {
+            // already overlapping, separate within the time step
+            let w = relative_velocity - relative_position / time_step;
+            let w_length = w.magnitude();
+            let unit_w = w / w_length.max(EPSILON);
+            (Vector2::new(unit_w.y, -unit_w.x), unit_w * (combined_radius / time_step - w_length))
+        };
+        // reciprocating agents take half the avoidance each, others are avoided entirely
+        let share = if other.avoiding { 0.5 } else { 1.0 };
+        lines.push(Line {
+            point: velocity + u * share,
+            direction
+        });
+    }
+    let mut result = preferred.truncate();
+    let failed = linear_program_2(&lines, max_speed, preferred.truncate(), false, &mut result);
+    if failed < lines.len() {
+        linear_program_3(&lines, failed, max_speed, &mut result);
+    }
+    result.extend(preferred.z)
+}
+
+/// Shortens a velocity so that a time step of it ends on the navigation mesh
+/// takes   navigation mesh as NavMesh
+///         position as Vector3<f32>
+///         velocity as Vector3<f32>
+///         time step in seconds as f32
+/// returns velocity ending on the mesh, unchanged when the mesh has no closest point, as Vector3<f32>
+pub fn clamp_to_nav_mesh(nav_mesh: &NavMesh, position: Vector3<f32>, velocity: Vector3<f32>, time_step: f32) -> Vector3<f32> {
+    let target = position + velocity * time_step;
+    match nav_mesh.closest_point(NavVec3::new(target.x, target.y, target.z), NavQuery::Accuracy) {
+        Some(point) => (Vector3::new(point.x, point.y, point.z) - position) / time_step,
+        None => velocity
+    }
+}
+
+fn determinant(a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+// optimises along one line within the speed circle and the lines before it
+fn linear_program_1(lines: &[Line], line: usize, radius: f32, optimal: Vector2<f32>, direction_optimal: bool,
+        result: &mut Vector2<f32>) -> bool {
+    let dot = lines[line].point.dot(lines[line].direction);
+    let discriminant = dot * dot + radius * radius - lines[line].point.magnitude2();
+    if discriminant < 0.0 {
+        // the speed circle misses the line entirely
+        return false;
+    }
+    let discriminant = discriminant.sqrt();
+    let mut left = -dot - discriminant;
+    let mut right = -dot + discriminant;
+    for other in &lines[..line] {
+        let denominator = determinant(lines[line].direction, other.direction);
+        let numerator = determinant(other.direction, lines[line].point - other.point);
+        if denominator.abs() <= EPSILON {
+            // parallel lines, either this one is entirely allowed or entirely not
+            if numerator < 0.0 {
+                return false;
+            }
+            continue;
+        }
+        let t = numerator / denominator;
+        if denominator >= 0.0 {
+            right = right.min(t);
+        } else {
+            left = left.max(t);
+        }
+        if left > right {
+            return false;
+        }
+    }
+    let point = lines[line].point;
+    let direction = lines[line].direction;
+    *result = if direction_optimal {
+        if optimal.dot(direction) > 0.0 { point + direction * right } else { point + direction * left }
+    } else {
+        let t = direction.dot(optimal - point);
+        point + direction * t.max(left).min(right)
+    };
+    true
+}
+
+// finds the allowed velocity closest to the optimal one, returning the first line that could not be met
+fn linear_program_2(lines: &[Line], radius: f32, optimal: Vector2<f32>, direction_optimal: bool, result: &mut Vector2<f32>) -> usize {
+    *result = if direction_optimal {
+        optimal * radius
+    } else if optimal.magnitude2() > radius * radius {
+        optimal.normalize() * radius
+    } else {
+        optimal
+    };
+    for line in 0..lines.len() {
+        if determinant(lines[line].direction, lines[line].point - *result) > 0.0 {
+            let previous = *result;
+            if !linear_program_1(lines, line, radius, optimal, direction_optimal, result) {
+                *result = previous;
+                return line;
+            }
+        }
+    }
+    lines.len()
+}
+
+// when no velocity meets every line, minimises how far the worst line is broken
+fn linear_program_3(lines: &[Line], begin: usize, radius: f32, result: &mut Vector2<f32>) {
+    let mut distance = 0.0;
+    for line in begin..lines.len() {
+        if determinant(lines[line].direction, lines[line].point - *result) <= distance {
+            continue;
+        }
+        let mut projected = Vec::with_capacity(line);
+        for other in &lines[..line] {
+            let denominator = determinant(lines[line].direction, other.direction);
+            let point = if denominator.abs() <= EPSILON {
+                if lines[line].direction.dot(other.direction) > 0.0 {
+                    // same direction, this line already covers the other
+                    continue;
+                }
+                (lines[line].point + other.point) * 0.5
+            } else {
+                lines[line].point + lines[line].direction
+                    * (determinant(other.direction, lines[line].point - other.point) / denominator)
+            };
+            projected.push(Line {
+                point,
+                direction: (other.direction - lines[line].direction).normalize()
+            });
+        }
+        let previous = *result;
+        let direction = Vector2::new(-lines[line].direction.y, lines[line].direction.x);
+        if linear_program_2(&projected, radius, direction, true, result) < projected.len() {
+            // only fails through rounding, keep the previous result
+            *result = previous;
+        }
+        distance = determinant(lines[line].direction, lines[line].point - *result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::Entity;
+
+    fn agent(entity: usize, position: [f32; 3]) -> SteeringAgent {
+        SteeringAgent {
+            entity: Entity::new(entity),
+            position: position.into(),
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+            radius: 0.25,
+            avoiding: true
+        }
+    }
+
+    #[test]
+    fn head_on_agents_pass_each_other_without_overlapping() {
+        let avoidance = Avoidance::new(2.0, 3.0);
+        let time_step = 0.05;
+        let goals = [Vector3::new(2.0, 0.0, 0.0), Vector3::new(-2.0, 0.0, 0.0)];
+        let mut agents = vec![agent(0, [-2.0, 0.0, 0.0]), agent(1, [2.0, 0.0, 0.0])];
+        let mut closest = f32::INFINITY;
+        for _ in 0..300 {
+            let snapshot = agents.clone();
+            for (index, agent) in agents.iter_mut().enumerate() {
+                let offset = goals[index] - agent.position;
+                let preferred = if offset.magnitude() > 0.05 { offset.normalize() } else { offset / time_step };
+                agent.velocity = avoid(&snapshot, index, preferred, 1.0, &avoidance, time_step);
+                agent.position += agent.velocity * time_step;
+            }
+            closest = closest.min((agents[0].position - agents[1].position).magnitude());
+        }
+        assert!(closest >= 0.5 - 1.0e-3, "came within {}", closest);
+        for (agent, goal) in agents.iter().zip(goals.iter()) {
+            assert!((agent.position - goal).magnitude() < 0.1, "stopped at {:?}", agent.position);
+        }
+    }
+
+    #[test]
+    fn clamping_stops_a_step_at_the_edge_of_the_mesh() {
+        let vertices: Vec<NavVec3> = vec![(1.0, 1.0, 0.0).into(), (-1.0, 1.0, 0.0).into(), (1.0, -1.0, 0.0).into(), (-1.0, -1.0, 0.0).into()];
+        let nav_mesh = NavMesh::new(vertices, vec![(0, 1, 2).into(), (1, 2, 3).into()]).unwrap();
+        let position = Vector3::new(0.9, 0.0, 0.0);
+        let across = clamp_to_nav_mesh(&nav_mesh, position, Vector3::new(1.0, 0.0, 0.0), 0.5);
+        assert!((across - Vector3::new(0.2, 0.0, 0.0)).magnitude() < 1.0e-4, "{:?}", across);
+        let along = clamp_to_nav_mesh(&nav_mesh, position, Vector3::new(-1.0, 0.5, 0.0), 0.5);
+        assert!((along - Vector3::new(-1.0, 0.5, 0.0)).magnitude() < 1.0e-4, "{:?}", along);
+    }
+}
@@ -0,0 +1,739 @@
+use std::convert::TryInto;
+
+const DDS_MAGIC: &[u8] = b"DDS ";
+const KTX2_MAGIC: &[u8] = &[0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BlockFormat {
+    Bc1,
+    Bc3,
+    Bc5,
+    Bc7
+}
+impl BlockFormat {
+    pub fn get_block_size(&self) -> usize {
+        match self {
+            BlockFormat::Bc1 => 8,
+            BlockFormat::Bc3 | BlockFormat::Bc5 | BlockFormat::Bc7 => 16
+        }
+    }
+    fn from_four_cc(four_cc: &[u8]) -> Option<BlockFormat> {
+        match four_cc {
+            b"DXT1" => Some(BlockFormat::Bc1),
+            b"DXT5" => Some(BlockFormat::Bc3),
+            b"ATI2" | b"BC5U" => Some(BlockFormat::Bc5),
+            _ => None
+        }
+    }
+    fn from_dxgi_format(format: u32) -> Option<BlockFormat> {
+        match format {
+            70..=72 => Some(BlockFormat::Bc1),
+            76..=78 => Some(BlockFormat::Bc3),
+            82..=83 => Some(BlockFormat::Bc5),
+            97..=99 => Some(BlockFormat::Bc7),
+            _ => None
+        }
+    }
+    fn from_vk_format(format: u32) -> Option<BlockFormat> {
+        match format {
+            131..=134 => Some(BlockFormat::Bc1),
+            137..=138 => Some(BlockFormat::Bc3),
+            141 => Some(BlockFormat::Bc5),
+            145..=146 => Some(BlockFormat::Bc7),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CompressedImage {
+    format: BlockFormat,
+    width: u32,
+    height: u32,
+    top_down: bool,
+    levels: Vec<Vec<u8>>
+}
+#[allow(dead_code)]
+impl CompressedImage {
+    /// Checks if a file extension names a compressed texture container
+    /// takes   file path as str
+    /// returns whether the file is a dds or ktx2 container as bool
+    pub fn is_container(file_path: &str) -> bool {
+        let path = std::path::Path::new(file_path);
+        matches!(path.extension().and_then(std::ffi::OsStr::to_str), Some("dds") | Some("ktx2"))
+    }
+    /// Parses a dds or ktx2 container holding a bcn payload
+    /// takes   file contents as [u8]
+    ///         file path for error messages as str
+    /// returns every stored mip level as CompressedImage
+    pub fn from_bytes(bytes: &[u8], file_path: &str) -> CompressedImage {
+        if bytes.starts_with(DDS_MAGIC) {
+            CompressedImage::from_dds(bytes, file_path)
+        } else if bytes.starts_with(KTX2_MAGIC) {
+            CompressedImage::from_ktx2(bytes, file_path)
+        } else {
+            panic!("Could not recognize the container of {}", file_path)
+        }
+    }
+    fn from_dds(bytes: &[u8], file_path: &str) -> CompressedImage {
+        let height = read_u32(bytes, 12, file_path);
+        let width = read_u32(bytes, 16, file_path);
+        let level_count = read_u32(bytes, 28, file_path).max(1);
+        let four_cc = read_bytes(bytes, 84, 4, file_path);
+        let (format, mut offset) = if four_cc == b"DX10" {
+            let dxgi_format = read_u32(bytes, 128, file_path);
+            match BlockFormat::from_dxgi_format(dxgi_format) {
+                Some(format) => (format, 148),
+                None => panic!("Could not recognize the dxgi format {} of {}", dxgi_format, file_path)
+            }
+        } else {
+            match BlockFormat::from_four_cc(four_cc) {
+                Some(format) => (format, 128),
+                None => panic!("Could not recognize the four cc {:?} of {}", String::from_utf8_lossy(four_cc), file_path)
+            }
+        };
+        let mut levels = Vec::new();
+        for level in 0..level_count {
+            let length = level_byte_length(format, width, height, level);
+            levels.push(read_bytes(bytes, offset, length, file_path).to_vec());
+            offset += length;
+        }
+        CompressedImage {
+            format,
+            width,
+            height,
+            top_down: true,
+            levels
+        }
+    }
+    fn from_ktx2(bytes: &[u8], file_path: &str) -> CompressedImage {
+        let vk_format = read_u32(bytes, 12, file_path);
+        let width = read_u32(bytes, 20, file_path);
+        let height = read_u32(bytes, 24, file_path).max(1);
+        let level_count = read_u32(bytes, 40, file_path).max(1);
+        let supercompression = read_u32(bytes, 44, file_path);
+        if supercompression != 0 {
+            panic!("Could not read supercompressed data of {}", file_path);
+        }
+        let format = match BlockFormat::from_vk_format(vk_format) {
+            Some(format) => format,
+            None => panic!("Could not recognize the vulkan format {} of {}", vk_format, file_path)
+        };
+        let key_values_offset = read_u32(bytes, 56, file_path) as usize;
+        let key_values_length = read_u32(bytes, 60, file_path) as usize;
+        let key_values = read_bytes(bytes, key_values_offset, key_values_length, file_path);
+        // images start at the top left unless the orientation says up
+        let top_down = match ktx2_value(key_values, b"KTXorientation") {
+            Some(orientation) => !orientation.starts_with(b"ru"),
+            None => true
+        };
+        let mut levels = Vec::new();
+        for level in 0..level_count as usize {
+            let index = 80 + level * 24;
+            let offset = read_u64(bytes, index, file_path) as usize;
+            let length = read_u64(bytes, index + 8, file_path) as usize;
+            levels.push(read_bytes(bytes, offset, length, file_path).to_vec());
+        }
+        CompressedImage {
+            format,
+            width,
+            height,
+            top_down,
+            levels
+        }
+    }
+    pub fn get_format(&self) -> BlockFormat {
+        self.format
+    }
+    pub fn get_dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+    pub fn is_top_down(&self) -> bool {
+        self.top_down
+    }
+    pub fn get_level_count(&self) -> u32 {
+        self.levels.len() as u32
+    }
+    pub fn get_level(&self, level: u32) -> &[u8] {
+        &self.levels[level as usize]
+    }
+    pub fn get_level_dimensions(&self, level: u32) -> (u32, u32) {
+        ((self.width >> level).max(1), (self.height >> level).max(1))
+    }
+    /// Decompresses a mip level on the cpu
+    /// takes   mip level as u32
+    /// returns rgba pixels with the first row at the bottom as Vec<u8>
+    pub fn decompress_level(&self, level: u32) -> Vec<u8> {
+        let (width, height) = self.get_level_dimensions(level);
+        let data = self.get_level(level);
+        let blocks_wide = width.div_ceil(4) as usize;
+        let block_size = self.format.get_block_size();
+        let mut pixels = vec![0u8; (width * height * 4) as usize];
+        for (index, block) in data.chunks(block_size).enumerate() {
+            let block_x = (index % blocks_wide) * 4;
+            let block_y = (index / blocks_wide) * 4;
+            if block_y >= height as usize {
+                break;
+            }
+            let texels = match self.format {
+                BlockFormat::Bc1 => decode_bc1(block),
+                BlockFormat::Bc3 => decode_bc3(block),
+                BlockFormat::Bc5 => decode_bc5(block),
+                BlockFormat::Bc7 => decode_bc7(block)
+            };
+            for (texel, color) in texels.iter().enumerate() {
+                let x = block_x + texel % 4;
+                let y = block_y + texel / 4;
+                if x >= width as usize || y >= height as usize {
+                    continue;
+                }
+                // flip so rows run bottom up like the rest of the textures
+                let row = if self.top_down { height as usize - 1 - y } else { y };
+                let offset = (row * width as usize + x) * 4;
+                pixels[offset..offset + 4].copy_from_slice(color);
+            }
+        }
+        pixels
+    }
+}
+
+fn level_byte_length(format: BlockFormat, width: u32, height: u32, level: u32) -> usize {
+    let blocks_wide = (width >> level).max(1).div_ceil(4) as usize;
+    let blocks_high = (height >> level).max(1).div_ceil(4) as usize;
+    blocks_wide * blocks_high * format.get_block_size()
+}
+
+fn read_bytes<'a>(bytes: &'a [u8], offset: usize, length: usize, file_path: &str) -> &'a [u8] {
+    match bytes.get(offset..offset + length) {
+        Some(slice) => slice,
+        None => panic!("Couldn't read {}: unexpected end of file", file_path)
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize, file_path: &str) -> u32 {
+    u32::from_le_bytes(read_bytes(bytes, offset, 4, file_path).try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize, file_path: &str) -> u64 {
+    u64::from_le_bytes(read_bytes(bytes, offset, 8, file_path).try_into().unwrap())
+}
+
+fn ktx2_value<'a>(key_values: &'a [u8], key: &[u8]) -> Option<&'a [u8]> {
+    let mut offset = 0;
+    while offset + 4 <= key_values.len() {
+        let length = u32::from_le_bytes(key_values[offset..offset + 4].try_into().unwrap()) as usize;
+        let entry = key_values.get(offset + 4..offset + 4 + length)?;
+        // entries are a nul terminated key followed by the value
+        if entry.len() > key.len() && entry.starts_with(key) && entry[key.len()] == 0 {
+            return Some(&entry[key.len() + 1..]);
+        }
+        offset += 4 + ((length + 3) & !3);
+    }
+    None
+}
+
+fn expand_565(color: u16) -> [u8; 3] {
+    let r = ((color >> 11) & 0x1F) as u32;
+    let g = ((color >> 5) & 0x3F) as u32;
+    let b = (color & 0x1F) as u32;
+    [((r * 255 + 15) / 31) as u8, ((g * 255 + 31) / 63) as u8, ((b * 255 + 15) / 31) as u8]
+}
+
+/// Decodes the color half of a bc1, bc2 or bc3 block
+/// takes   eight color bytes as [u8]
+///         whether the three color punch through mode is allowed as bool
+/// returns texels in row order as [[u8; 4]; 16]
+fn decode_color_block(block: &[u8], allow_punch_through: bool) -> [[u8; 4]; 16] {
+    let color0 = u16::from_le_bytes([block[0], block[1]]);
+    let color1 = u16::from_le_bytes([block[2], block[3]]);
+    let c0 = expand_565(color0);
+    let c1 = expand_565(color1);
+    let mix = |a: u8, b: u8, wa: u32, wb: u32| ((a as u32 * wa + b as u32 * wb) / (wa + wb)) as u8;
+    let mut palette = [[0u8; 4]; 4];
+    palette[0] = [c0[0], c0[1], c0[2], 255];
+    palette[1] = [c1[0], c1[1], c1[2], 255];
+    if color0 > color1 || !allow_punch_through {
+        for channel in 0..3 {
+            palette[2][channel] = mix(c0[channel], c1[channel], 2, 1);
+            palette[3][channel] = mix(c0[channel], c1[channel], 1, 2);
+        }
+        palette[2][3] = 255;
+        palette[3][3] = 255;
+    } else {
+        for channel in 0..3 {
+            palette[2][channel] = mix(c0[channel], c1[channel], 1, 1);
+        }
+        palette[2][3] = 255;
+        palette[3] = [0, 0, 0, 0];
+    }
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    let mut texels = [[0u8; 4]; 16];
+    for (texel, color) in texels.iter_mut().enumerate() {
+        *color = palette[((indices >> (texel * 2)) & 0x3) as usize];
+    }
+    texels
+}
+
+/// Decodes a bc3 alpha or bc4 channel block
+/// takes   eight channel bytes as [u8]
+/// returns channel values in row order as [u8; 16]
+fn decode_channel_block(block: &[u8]) -> [u8; 16] {
+    let a0 = block[0] as u32;
+    let a1 = block[1] as u32;
+    let mut palette = [0u8; 8];
+    palette[0] = a0 as u8;
+    palette[1] = a1 as u8;
+    if a0 > a1 {
+        for step in 1..7 {
+            palette[step + 1] = ((a0 * (7 - step as u32) + a1 * step as u32) / 7) as u8;
+        }
+    } else {
+        for step in 1..5 {
+            palette[step + 1] = ((a0 * (5 - step as u32) + a1 * step as u32) / 5) as u8;
+        }
+        palette[6] = 0;
+        palette[7] = 255;
+    }
+    let mut bits = 0u64;
+    for (index, byte) in block[2..8].iter().enumerate() {
+        bits |= (*byte as u64) << (index * 8);
+    }
+    let mut values = [0u8; 16];
+    for (texel, value) in values.iter_mut().enumerate() {
+        *value = palette[((bits >> (texel * 3)) & 0x7) as usize];
+    }
+    values
+}
+
+fn decode_bc1(block: &[u8]) -> [[u8; 4]; 16] {
+    decode_color_block(block, true)
+}
+
+fn decode_bc3(block: &[u8]) -> [[u8; 4]; 16] {
+    let alpha = decode_channel_block(&block[0..8]);
+    let mut texels = decode_color_block(&block[8..16], false);
+    for (color, value) in texels.iter_mut().zip(alpha.iter()) {
+        color[3] = *value;
+    }
+    texels
+}
+
+fn decode_bc5(block: &[u8]) -> [[u8; 4]; 16] {
+    let red = decode_channel_block(&block[0..8]);
+    let green = decode_channel_block(&block[8..16]);
+    let mut texels = [[0u8; 4]; 16];
+    for (texel, color) in texels.iter_mut().enumerate() {
+        // two channel normals, rebuild z so the map reads like an uncompressed one
+        let x = red[texel] as f32 / 127.5 - 1.0;
+        let y = green[texel] as f32 / 127.5 - 1.0;
+        let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+        *color = [red[texel], green[texel], ((z + 1.0) * 127.5) as u8, 255];
+    }
+    texels
+}
+
+// layout of a bc7 mode, every field but the mode's own bits is read least significant bit first
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: usize,
+    rotation_bits: usize,
+    index_selection_bits: usize,
+    color_bits: usize,
+    alpha_bits: usize,
+    endpoint_p_bits: bool,
+    shared_p_bits: bool,
+    index_bits: usize,
+    secondary_index_bits: usize
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    Bc7Mode { subsets: 3, partition_bits: 4, rotation_bits: 0, index_selection_bits: 0, color_bits: 4, alpha_bits: 0,
+        endpoint_p_bits: true, shared_p_bits: false, index_bits: 3, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 6, alpha_bits: 0,
+        endpoint_p_bits: false, shared_p_bits: true, index_bits: 3, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 3, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 0,
+        endpoint_p_bits: false, shared_p_bits: false, index_bits: 2, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 0,
+        endpoint_p_bits: true, shared_p_bits: false, index_bits: 2, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 1, color_bits: 5, alpha_bits: 6,
+        endpoint_p_bits: false, shared_p_bits: false, index_bits: 2, secondary_index_bits: 3 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 0, color_bits: 7, alpha_bits: 8,
+        endpoint_p_bits: false, shared_p_bits: false, index_bits: 2, secondary_index_bits: 2 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 7,
+        endpoint_p_bits: true, shared_p_bits: false, index_bits: 4, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 5,
+        endpoint_p_bits: true, shared_p_bits: false, index_bits: 2, secondary_index_bits: 0 }
+];
+
+// subset of each texel for the two subset partitions
+const BC7_PARTITIONS_2: [[u8; 16]; 64] = [
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1], [0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1],
+    [0, 1, 1, 1, 0, 1, 1, 1, 0, 1, 1, 1, 0, 1, 1, 1], [0, 0, 0, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 1, 1], [0, 0, 1, 1, 0, 1, 1, 1, 0, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 1, 1, 1, 1, 1], [0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 1, 1], [0, 0, 1, 1, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 1, 1, 1, 1, 1, 1], [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 1, 1],
+    [0, 0, 0, 1, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1], [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1], [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1],
+    [0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 1, 0, 1, 1, 1, 1], [0, 1, 1, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 1, 0], [0, 1, 1, 1, 0, 0, 1, 1, 0, 0, 0, 1, 0, 0, 0, 0],
+    [0, 0, 1, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0], [0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 0, 0, 1, 1, 1, 0],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 0, 0], [0, 1, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 0, 1],
+    [0, 0, 1, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0], [0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 1, 0, 0],
+    [0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0], [0, 0, 1, 1, 0, 1, 1, 0, 0, 1, 1, 0, 1, 1, 0, 0],
+    [0, 0, 0, 1, 0, 1, 1, 1, 1, 1, 1, 0, 1, 0, 0, 0], [0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0],
+    [0, 1, 1, 1, 0, 0, 0, 1, 1, 0, 0, 0, 1, 1, 1, 0], [0, 0, 1, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 1, 0, 0],
+    [0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1], [0, 0, 0, 0, 1, 1, 1, 1, 0, 0, 0, 0, 1, 1, 1, 1],
+    [0, 1, 0, 1, 1, 0, 1, 0, 0, 1, 0, 1, 1, 0, 1, 0], [0, 0, 1, 1, 0, 0, 1, 1, 1, 1, 0, 0, 1, 1, 0, 0],
+    [0, 0, 1, 1, 1, 1, 0, 0, 0, 0, 1, 1, 1, 1, 0, 0], [0, 1, 0, 1, 0, 1, 0, 1, 1, 0, 1, 0, 1, 0, 1, 0],
+    [0, 1, 1, 0, 1, 0, 0, 1, 0, 1, 1, 0, 1, 0, 0, 1], [0, 1, 0, 1, 1, 0, 1, 0, 1, 0, 1, 0, 0, 1, 0, 1],
+    [0, 1, 1, 1, 0, 0, 1, 1, 1, 1, 0, 0, 1, 1, 1, 0], [0, 0, 0, 1, 0, 0, 1, 1, 1, 1, 0, 0, 1, 0, 0, 0],
+    [0, 0, 1, 1, 0, 0, 1, 0, 0, 1, 0, 0, 1, 1, 0, 0], [0, 0, 1, 1, 1, 0, 1, 1, 1, 1, 0, 1, 1, 1, 0, 0],
+    [0, 1, 1, 0, 1, 0, 0, 1, 1, 0, 0, 1, 0, 1, 1, 0], [0, 0, 1, 1, 1, 1, 0, 0, 1, 1, 0, 0, 0, 0, 1, 1],
+    [0, 1, 1, 0, 0, 1, 1, 0, 1, 0, 0, 1, 1, 0, 0, 1], [0, 0, 0, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 0, 0, 1, 1, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0], [0, 0, 1, 0, 0, 1, 1, 1, 0, 0, 1, 0, 0, 0, 0, 0],
+    [0, 0, 0, 0, 0, 0, 1, 0, 0, 1, 1, 1, 0, 0, 1, 0], [0, 0, 0, 0, 0, 1, 0, 0, 1, 1, 1, 0, 0, 1, 0, 0],
+    [0, 1, 1, 0, 1, 1, 0, 0, 1, 0, 0, 1, 0, 0, 1, 1], [0, 0, 1, 1, 0, 1, 1, 0, 1, 1, 0, 0, 1, 0, 0, 1],
+    [0, 1, 1, 0, 0, 0, 1, 1, 1, 0, 0, 1, 1, 1, 0, 0], [0, 0, 1, 1, 1, 0, 0, 1, 1, 1, 0, 0, 0, 1, 1, 0],
+    [0, 1, 1, 0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 0, 0, 1], [0, 1, 1, 0, 0, 0, 1, 1, 0, 0, 1, 1, 1, 0, 0, 1],
+    [0, 1, 1, 1, 1, 1, 1, 0, 1, 0, 0, 0, 0, 0, 0, 1], [0, 0, 0, 1, 1, 0, 0, 0, 1, 1, 1, 0, 0, 1, 1, 1],
+    [0, 0, 0, 0, 1, 1, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1], [0, 0, 1, 1, 0, 0, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0],
+    [0, 0, 1, 0, 0, 0, 1, 0, 1, 1, 1, 0, 1, 1, 1, 0], [0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 1, 1, 0, 1, 1, 1]
+];
+
+// subset of each texel for the three subset partitions
+const BC7_PARTITIONS_3: [[u8; 16]; 64] = [
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 1, 2, 2, 2, 2], [0, 0, 0, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 2, 0, 0, 1, 2, 2, 1, 1, 2, 2, 1, 1], [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2], [0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 2, 2],
+    [0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1], [0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2], [0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2], [0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2],
+    [0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2], [0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2],
+    [0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2, 1, 2, 2, 2], [0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0, 2, 2, 2, 0],
+    [0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2], [0, 1, 1, 1, 0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0],
+    [0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2], [0, 0, 2, 2, 0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1],
+    [0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2, 0, 2, 2, 2], [0, 0, 0, 1, 0, 0, 0, 1, 2, 2, 2, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2], [0, 0, 0, 0, 1, 1, 0, 0, 2, 2, 1, 0, 2, 2, 1, 0],
+    [0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1, 0, 0, 0, 0], [0, 0, 1, 2, 0, 0, 1, 2, 1, 1, 2, 2, 2, 2, 2, 2],
+    [0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1, 0, 1, 1, 0], [0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1],
+    [0, 0, 2, 2, 1, 1, 0, 2, 1, 1, 0, 2, 0, 0, 2, 2], [0, 1, 1, 0, 0, 1, 1, 0, 2, 0, 0, 2, 2, 2, 2, 2],
+    [0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1], [0, 0, 0, 0, 2, 0, 0, 0, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 2, 2, 2], [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 0, 0, 1, 2, 0, 0, 2, 2, 0, 2, 2, 2], [0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0], [0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0],
+    [0, 1, 2, 0, 2, 0, 1, 2, 1, 2, 0, 1, 0, 1, 2, 0], [0, 0, 1, 1, 2, 2, 0, 0, 1, 1, 2, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0, 1, 1], [0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1], [0, 0, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2, 1, 1, 2, 2],
+    [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 1, 1], [0, 2, 2, 0, 1, 2, 2, 1, 0, 2, 2, 0, 1, 2, 2, 1],
+    [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 0, 1, 0, 1], [0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1],
+    [0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2], [0, 2, 2, 2, 0, 1, 1, 1, 0, 2, 2, 2, 0, 1, 1, 1],
+    [0, 0, 0, 2, 1, 1, 1, 2, 0, 0, 0, 2, 1, 1, 1, 2], [0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 2, 2, 2, 0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2], [0, 0, 0, 2, 1, 1, 1, 2, 1, 1, 1, 2, 0, 0, 0, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2], [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2, 2, 2, 2, 2], [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2],
+    [0, 0, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2], [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2],
+    [0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1], [0, 2, 2, 2, 1, 2, 2, 2, 0, 2, 2, 2, 1, 2, 2, 2],
+    [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2], [0, 1, 1, 1, 2, 0, 1, 1, 2, 2, 0, 1, 2, 2, 2, 0]
+];
+
+// texel whose index drops its top bit in the second subset of the two subset partitions
+const BC7_ANCHORS_2: [usize; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,
+    15, 2, 8, 2, 2, 8, 8, 15, 2, 8, 2, 2, 8, 8, 2, 2,
+    15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6,
+    6, 2, 6, 8, 15, 15, 2, 2, 15, 15, 15, 15, 15, 2, 2, 15
+];
+
+// anchor texels of the second and third subsets of the three subset partitions
+const BC7_ANCHORS_3: [[usize; 2]; 64] = [
+    [3, 15], [3, 8], [15, 8], [15, 3], [8, 15], [3, 15], [15, 3], [15, 8],
+    [8, 15], [8, 15], [6, 15], [6, 15], [6, 15], [5, 15], [3, 15], [3, 8],
+    [3, 15], [3, 8], [8, 15], [15, 3], [3, 15], [3, 8], [6, 15], [10, 8],
+    [5, 3], [8, 15], [8, 6], [6, 10], [8, 15], [5, 15], [15, 10], [15, 8],
+    [8, 15], [15, 3], [3, 15], [5, 10], [6, 10], [10, 8], [8, 9], [15, 10],
+    [15, 6], [3, 15], [15, 8], [5, 15], [15, 3], [15, 6], [15, 6], [15, 8],
+    [3, 15], [15, 3], [5, 15], [5, 15], [5, 15], [8, 15], [5, 15], [10, 15],
+    [5, 15], [10, 15], [8, 15], [13, 15], [15, 3], [12, 15], [3, 15], [3, 8]
+];
+
+const BC7_WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const BC7_WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const BC7_WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+// reads fields of a block least significant bit first
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize
+}
+impl BitReader<'_> {
+    fn read(&mut self, count: usize) -> u8 {
+        let mut value = 0u32;
+        for bit in 0..count {
+            let position = self.position + bit;
+            value |= (((self.bytes[position / 8] >> (position % 8)) & 1) as u32) << bit;
+        }
+        self.position += count;
+        value as u8
+    }
+}
+
+// widens an endpoint to eight bits by repeating its top bits below it
+fn expand_bits(value: u8, bits: usize) -> u8 {
+    let value = (value as u32) << (8 - bits);
+    (value | (value >> bits)) as u8
+}
+
+fn interpolate_bc7(a: u8, b: u8, index: u8, bits: usize) -> u8 {
+    let weight = match bits {
+        2 => BC7_WEIGHTS_2[index as usize],
+        3 => BC7_WEIGHTS_3[index as usize],
+        _ => BC7_WEIGHTS_4[index as usize]
+    };
+    (((64 - weight) * a as u32 + weight * b as u32 + 32) >> 6) as u8
+}
+
+/// Decodes a bc7 block in any of its eight modes, reserved modes decode to transparent black
+/// takes   sixteen block bytes as [u8]
+/// returns texels in row order as [[u8; 4]; 16]
+fn decode_bc7(block: &[u8]) -> [[u8; 4]; 16] {
+    // the mode is the number of zero bits before the first set bit
+    let mode_index = match (0..8).find(|bit| block[0] & (1 << bit) != 0) {
+        Some(mode_index) => mode_index,
+        None => return [[0u8; 4]; 16]
+    };
+    let mode = &BC7_MODES[mode_index];
+    let mut bits = BitReader { bytes: block, position: mode_index + 1 };
+    let partition = bits.read(mode.partition_bits) as usize;
+    let rotation = bits.read(mode.rotation_bits);
+    let index_selection = bits.read(mode.index_selection_bits);
+    let endpoint_count = mode.subsets * 2;
+    let mut endpoints = [[0u8; 4]; 6];
+    for channel in 0..3 {
+        for endpoint in endpoints.iter_mut().take(endpoint_count) {
+            endpoint[channel] = bits.read(mode.color_bits);
+        }
+    }
+    for endpoint in endpoints.iter_mut().take(endpoint_count) {
+        endpoint[3] = bits.read(mode.alpha_bits);
+    }
+    // p bits add a shared lowest bit to every channel of an endpoint, or of both endpoints of a subset
+    let (mut color_bits, mut alpha_bits) = (mode.color_bits, mode.alpha_bits);
+    if mode.endpoint_p_bits || mode.shared_p_bits {
+        let mut p_bits = [0u8; 6];
+        if mode.endpoint_p_bits {
+            for p_bit in p_bits.iter_mut().take(endpoint_count) {
+                *p_bit = bits.read(1);
+            }
+        } else {
+            for subset in 0..mode.subsets {
+                let p_bit = bits.read(1);
+                p_bits[subset * 2] = p_bit;
+                p_bits[subset * 2 + 1] = p_bit;
+            }
+        }
+        for (endpoint, p_bit) in endpoints.iter_mut().zip(p_bits.iter()).take(endpoint_count) {
+            for channel in endpoint.iter_mut() {
+                *channel = (*channel << 1) | p_bit;
+            }
+        }
+        color_bits += 1;
+        if alpha_bits > 0 {
+            alpha_bits += 1;
+        }
+    }
+    for endpoint in endpoints.iter_mut().take(endpoint_count) {
+        for channel in endpoint.iter_mut().take(3) {
+            *channel = expand_bits(*channel, color_bits);
+        }
+        endpoint[3] = if alpha_bits > 0 { expand_bits(endpoint[3], alpha_bits) } else { 255 };
+    }
+    let subset_of = |texel: usize| match mode.subsets {
+        1 => 0,
+        2 => BC7_PARTITIONS_2[partition][texel] as usize,
+        _ => BC7_PARTITIONS_3[partition][texel] as usize
+    };
+    // the first texel of each subset drops the top bit of its index, always reading as the low half
+    let is_anchor = |texel: usize| texel == 0 || match mode.subsets {
+        1 => false,
+        2 => texel == BC7_ANCHORS_2[partition],
+        _ => BC7_ANCHORS_3[partition].contains(&texel)
+    };
+    let mut indices = [0u8; 16];
+    for (texel, index) in indices.iter_mut().enumerate() {
+        *index = bits.read(mode.index_bits - is_anchor(texel) as usize);
+    }
+    let mut secondary_indices = [0u8; 16];
+    if mode.secondary_index_bits > 0 {
+        for (texel, index) in secondary_indices.iter_mut().enumerate() {
+            *index = bits.read(mode.secondary_index_bits - (texel == 0) as usize);
+        }
+    }
+    let mut texels = [[0u8; 4]; 16];
+    for (texel, color) in texels.iter_mut().enumerate() {
+        let subset = subset_of(texel);
+        let (start, end) = (endpoints[subset * 2], endpoints[subset * 2 + 1]);
+        // modes with two index sets pick which one colors and which one sets alpha
+        let ((color_index, color_index_bits), (alpha_index, alpha_index_bits)) = if mode.secondary_index_bits == 0 {
+            ((indices[texel], mode.index_bits), (indices[texel], mode.index_bits))
+        } else if index_selection == 0 {
+            ((indices[texel], mode.index_bits), (secondary_indices[texel], mode.secondary_index_bits))
+        } else {
+            ((secondary_indices[texel], mode.secondary_index_bits), (indices[texel], mode.index_bits))
+        };
+        for channel in 0..3 {
+            color[channel] = interpolate_bc7(start[channel], end[channel], color_index, color_index_bits);
+        }
+        color[3] = interpolate_bc7(start[3], end[3], alpha_index, alpha_index_bits);
+        // rotation stores a color channel in alpha for better precision, swap it back
+        if rotation > 0 {
+            color.swap(rotation as usize - 1, 3);
+        }
+    }
+    texels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // packs fields least significant bit first, the way bc7 blocks are read
+    struct BitWriter {
+        bytes: [u8; 16],
+        position: usize
+    }
+    impl BitWriter {
+        fn new() -> BitWriter {
+            BitWriter {
+                bytes: [0u8; 16],
+                position: 0
+            }
+        }
+        fn write(&mut self, value: u32, count: usize) {
+            for bit in 0..count {
+                let position = self.position + bit;
+                self.bytes[position / 8] |= ((value.checked_shr(bit as u32).unwrap_or(0) & 1) as u8) << (position % 8);
+            }
+            self.position += count;
+        }
+    }
+
+    #[test]
+    fn bc7_anchors_lie_in_their_subsets() {
+        for partition in 0..64 {
+            assert_eq!(BC7_PARTITIONS_2[partition][0], 0);
+            assert_eq!(BC7_PARTITIONS_2[partition][BC7_ANCHORS_2[partition]], 1, "two subset partition {}", partition);
+            assert_eq!(BC7_PARTITIONS_3[partition][0], 0);
+            assert_eq!(BC7_PARTITIONS_3[partition][BC7_ANCHORS_3[partition][0]], 1, "three subset partition {}", partition);
+            assert_eq!(BC7_PARTITIONS_3[partition][BC7_ANCHORS_3[partition][1]], 2, "three subset partition {}", partition);
+        }
+    }
+
+    #[test]
+    fn bc7_mode_6_interpolates_between_endpoints_with_p_bits() {
+        let mut block = BitWriter::new();
+        block.write(1 << 6, 7);
+        // red, green, blue and alpha of both endpoints, then their p bits
+        for (start, end) in [(10, 100), (20, 110), (30, 120), (40, 127)].iter() {
+            block.write(*start, 7);
+            block.write(*end, 7);
+        }
+        block.write(1, 1);
+        block.write(0, 1);
+        // the anchor texel has three index bits, the last texel picks the far endpoint
+        block.write(0, 3);
+        for _ in 1..15 {
+            block.write(8, 4);
+        }
+        block.write(15, 4);
+        let texels = decode_bc7(&block.bytes);
+        assert_eq!(texels[0], [21, 41, 61, 81]);
+        assert_eq!(texels[15], [200, 220, 240, 254]);
+        // weight 34 of 64 between the endpoints
+        let middle = |a: u32, b: u32| (((64 - 34) * a + 34 * b + 32) >> 6) as u8;
+        assert_eq!(texels[7], [middle(21, 200), middle(41, 220), middle(61, 240), middle(81, 254)]);
+    }
+
+    #[test]
+    fn bc7_mode_5_rotates_alpha_into_red() {
+        let mut block = BitWriter::new();
+        block.write(1 << 5, 6);
+        // rotation one swaps red and alpha
+        block.write(1, 2);
+        for value in [127, 0, 64, 64, 0, 0].iter() {
+            block.write(*value, 7);
+        }
+        block.write(17, 8);
+        block.write(17, 8);
+        block.write(0, 31);
+        block.write(0, 31);
+        let texels = decode_bc7(&block.bytes);
+        for texel in texels.iter() {
+            assert_eq!(*texel, [17, 129, 0, 255]);
+        }
+    }
+
+    #[test]
+    fn bc7_mode_1_splits_texels_by_partition_with_shared_p_bits() {
+        let mut block = BitWriter::new();
+        block.write(1 << 1, 2);
+        // partition 13 puts the top two rows in subset 0 and the bottom two in subset 1
+        block.write(13, 6);
+        for channel in 0..3 {
+            for endpoint in 0..4 {
+                block.write(if endpoint < 2 { 0 } else { 63 - channel }, 6);
+            }
+        }
+        block.write(0, 1);
+        block.write(1, 1);
+        block.write(0, 46);
+        let texels = decode_bc7(&block.bytes);
+        for (texel, color) in texels.iter().enumerate() {
+            if texel < 8 {
+                assert_eq!(*color, [0, 0, 0, 255]);
+            } else {
+                assert_eq!(*color, [255, expand_bits(125, 7), expand_bits(123, 7), 255]);
+            }
+        }
+    }
+
+    #[test]
+    fn bc7_mode_4_index_selection_swaps_color_and_alpha_indices() {
+        let mut block = BitWriter::new();
+        block.write(1 << 4, 5);
+        block.write(0, 2);
+        block.write(1, 1);
+        for _ in 0..3 {
+            block.write(0, 5);
+            block.write(31, 5);
+        }
+        block.write(0, 6);
+        block.write(63, 6);
+        // two bit indices all at the far endpoint now set alpha, three bit ones at zero set color
+        block.write(1, 1);
+        for _ in 1..16 {
+            block.write(3, 2);
+        }
+        block.write(0, 47);
+        let texels = decode_bc7(&block.bytes);
+        assert_eq!(texels[0], [0, 0, 0, interpolate_bc7(0, 255, 1, 2)]);
+        assert_eq!(texels[5], [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn bc7_reserved_mode_decodes_to_transparent_black() {
+        assert_eq!(decode_bc7(&[0u8; 16]), [[0u8; 4]; 16]);
+    }
+
+    #[test]
+    fn bc7_levels_decompress_on_the_cpu() {
+        let image = CompressedImage {
+            format: BlockFormat::Bc7,
+            width: 6,
+            height: 5,
+            top_down: true,
+            levels: vec![[0x40u8; 16].iter().cycle().take(16 * 4).cloned().collect()]
+        };
+        assert_eq!(image.decompress_level(0).len(), 6 * 5 * 4);
+    }
+}
@@ -0,0 +1,149 @@
+use glium::{
+    implement_vertex,
+    Surface,
+    texture::{
+        MipmapsOption,
+        RawImage2d,
+        SrgbTexture2d
+    },
+    uniform,
+    VertexBuffer
+};
+use obj::TexturedVertex;
+
+use crate::etc::build_program;
+
+const CHECKER_SIZE: u32 = 256;
+const CHECKER_CELLS: u32 = 8;
+// normal lines as a fraction of the bounding radius
+const NORMAL_LENGTH: f32 = 0.15;
+
+#[derive(Debug, Copy, Clone)]
+pub struct DebugVertex {
+    position: [f32; 3]
+}
+implement_vertex!(DebugVertex, position);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RenderMode {
+    pub wireframe: bool,
+    pub normals: bool,
+    pub uv_checker: bool,
+    pub flat_normals: bool
+}
+#[allow(dead_code)]
+impl RenderMode {
+    pub fn new() -> RenderMode {
+        RenderMode {
+            wireframe: false,
+            normals: false,
+            uv_checker: false,
+            flat_normals: false
+        }
+    }
+    /// Describes the enabled switches for display
+    /// returns names of enabled switches, or NONE, as String
+    pub fn get_name(&self) -> String {
+        let names: Vec<&str> = [
+            (self.wireframe, "WIRE"),
+            (self.normals, "NORMALS"),
+            (self.uv_checker, "UV"),
+            (self.flat_normals, "FLAT")
+        ].iter()
+            .filter(|(enabled, _)| *enabled)
+            .map(|(_, name)| *name)
+            .collect();
+        if names.is_empty() {
+            "NONE".to_string()
+        } else {
+            names.join(" ")
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DebugRenderer {
+    program: glium::Program,
+    checker: SrgbTexture2d,
+    mode: RenderMode
+}
+#[allow(dead_code)]
+impl DebugRenderer {
+    pub fn new(display: &glium::Display, vertex_shader_file_path: &str, fragment_shader_file_path: &str) -> DebugRenderer {
+        DebugRenderer {
+            program: build_program(display, vertex_shader_file_path, fragment_shader_file_path),
+            checker: checker_texture(display, CHECKER_SIZE, CHECKER_CELLS),
+            mode: RenderMode::new()
+        }
+    }
+    pub fn get_mode(&self) -> RenderMode {
+        self.mode
+    }
+    pub fn get_mode_mut(&mut self) -> &mut RenderMode {
+        &mut self.mode
+    }
+    pub fn get_checker(&self) -> &SrgbTexture2d {
+        &self.checker
+    }
+    /// Draws the normal lines of an object
+    /// takes   drawing surface as glium::Frame
+    ///         normal lines as VertexBuffer<DebugVertex>
+    ///         model, view and perspective transformations as [[f32;4]; 4]
+    ///         draw parameters as glium::DrawParameters
+    pub fn draw_lines(&self, target: &mut glium::Frame, lines: &VertexBuffer<DebugVertex>, model: [[f32;4]; 4],
+            view: [[f32;4]; 4], perspective: [[f32;4]; 4], params: &glium::DrawParameters) {
+        target.draw(lines,
+            glium::index::NoIndices(glium::index::PrimitiveType::LinesList),
+            &self.program,
+            &uniform!{
+                model: model,
+                view: view,
+                perspective: perspective,
+                u_color: [1.0f32, 1.0, 0.0, 1.0]
+            },
+            params).unwrap();
+    }
+}
+
+/// Constructs a checker texture for inspecting texture coordinates, the bottom left cell is red
+/// takes   display as glium::Display
+///         texture size in pixels as u32
+///         cells along each side as u32
+/// returns checker texture as SrgbTexture2d
+pub fn checker_texture(display: &glium::Display, size: u32, cells: u32) -> SrgbTexture2d {
+    let cell_size = (size / cells).max(1);
+    let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+    // rows run bottom up to match texture coordinates
+    for y in 0..size {
+        for x in 0..size {
+            let (cell_x, cell_y) = (x / cell_size, y / cell_size);
+            let color = if cell_x == 0 && cell_y == 0 {
+                [255, 0, 0, 255]
+            } else if (cell_x + cell_y) % 2 == 0 {
+                [230, 230, 230, 255]
+            } else {
+                [40, 40, 40, 255]
+            };
+            pixels.extend_from_slice(&color);
+        }
+    }
+    let image = RawImage2d::from_raw_rgba(pixels, (size, size));
+    SrgbTexture2d::with_mipmaps(display, image, MipmapsOption::AutoGeneratedMipmaps).unwrap()
+}
+
+/// Constructs a line from each vertex along its normal
+/// takes   display as glium::Display
+///         vertices as [TexturedVertex]
+///         bounding radius of the mesh as f32
+/// returns line list as VertexBuffer<DebugVertex>
+pub fn normal_lines(display: &glium::Display, vertices: &[TexturedVertex], bounding_radius: f32) -> VertexBuffer<DebugVertex> {
+    let length = bounding_radius * NORMAL_LENGTH;
+    let mut lines = Vec::with_capacity(vertices.len() * 2);
+    for vertex in vertices {
+        let p = vertex.position;
+        let n = vertex.normal;
+        lines.push(DebugVertex { position: p });
+        lines.push(DebugVertex { position: [p[0] + n[0] * length, p[1] + n[1] * length, p[2] + n[2] * length] });
+    }
+    VertexBuffer::new(display, &lines).unwrap()
+}
@@ -0,0 +1,333 @@
+use navmesh::NavMesh;
+use rand::Rng;
+
+use crate::{
+    agent::NavAgent,
+    gfx::Graphic,
+    physics::{
+        collide,
+        overlapping_pairs,
+        Bounds,
+        PhysicsSettings,
+        RigidBody,
+        RollResult
+    },
+    scene::{
+        NodeId,
+        Scene,
+        SceneLight
+    },
+    steering::SteeringAgent,
+    transform::Transform
+};
+
+/// Handle of a thing in the world, the index its components are stored under
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Entity(usize);
+#[allow(dead_code)]
+impl Entity {
+    pub fn new(index: usize) -> Entity {
+        Entity(index)
+    }
+    pub fn get_index(&self) -> usize {
+        self.0
+    }
+}
+
+/// Components of one kind, stored by entity index, entities without one leave a gap
+#[derive(Debug, Clone)]
+pub struct ComponentStore<T> {
+    components: Vec<Option<T>>
+}
+#[allow(dead_code)]
+impl<T> ComponentStore<T> {
+    pub fn new() -> ComponentStore<T> {
+        ComponentStore {
+            components: Vec::new()
+        }
+    }
+    /// Gives an entity a component, replacing any it had
+    /// takes   entity as Entity
+    ///         component as T
+    pub fn insert(&mut self, entity: Entity, component: T) {
+        if self.components.len() <= entity.0 {
+            self.components.resize_with(entity.0 + 1, || None);
+        }
+        self.components[entity.0] = Some(component);
+    }
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        self.components.get_mut(entity.0)?.take()
+    }
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.components.get(entity.0)?.as_ref()
+    }
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.components.get_mut(entity.0)?.as_mut()
+    }
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.get(entity).is_some()
+    }
+    /// Iterates the entities having a component, in entity order
+    /// returns entities and their components as Iterator<Item = (Entity, &T)>
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.components.iter()
+            .enumerate()
+            .filter_map(|(index, component)| Some((Entity(index), component.as_ref()?)))
+    }
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.components.iter_mut()
+            .enumerate()
+            .filter_map(|(index, component)| Some((Entity(index), component.as_mut()?)))
+    }
+}
+
+/// Every entity of the world along with its components, each entity has a transform and any of a
+/// graphic, light, navigation agent and rigid body
+///
+/// The transform as it was at the start of the tick is kept for drawing between ticks, and the scene
+/// node drawing an entity belongs to the render system
+#[derive(Debug, Clone)]
+pub struct Entities {
+    transforms: Vec<Transform>,
+    previous_transforms: Vec<Transform>,
+    graphics: ComponentStore<Graphic>,
+    lights: ComponentStore<SceneLight>,
+    agents: ComponentStore<NavAgent>,
+    rigid_bodies: ComponentStore<RigidBody>,
+    nodes: ComponentStore<NodeId>,
+    // entities whose graphic or light changed since the last render
+    changed: Vec<Entity>
+}
+#[allow(dead_code)]
+impl Entities {
+    pub fn new() -> Entities {
+        Entities {
+            transforms: Vec::new(),
+            previous_transforms: Vec::new(),
+            graphics: ComponentStore::new(),
+            lights: ComponentStore::new(),
+            agents: ComponentStore::new(),
+            rigid_bodies: ComponentStore::new(),
+            nodes: ComponentStore::new(),
+            changed: Vec::new()
+        }
+    }
+    /// Creates an entity without components besides its transform
+    /// takes   transform relative to the world's table as Transform
+    /// returns entity as Entity
+    pub fn spawn(&mut self, transform: Transform) -> Entity {
+        self.transforms.push(transform);
+        self.previous_transforms.push(transform);
+        Entity(self.transforms.len() - 1)
+    }
+    pub fn len(&self) -> usize {
+        self.transforms.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+    /// Iterates every entity in the order they were spawned
+    /// returns entities as Iterator<Item = Entity>
+    pub fn iter(&self) -> impl Iterator<Item = Entity> {
+        (0..self.transforms.len()).map(Entity)
+    }
+    pub fn get_transform(&self, entity: Entity) -> &Transform {
+        match self.transforms.get(entity.0) {
+            Some(transform) => transform,
+            None => panic!("Could not find entity {}", entity.0)
+        }
+    }
+    pub fn get_transform_mut(&mut self, entity: Entity) -> &mut Transform {
+        match self.transforms.get_mut(entity.0) {
+            Some(transform) => transform,
+            None => panic!("Could not find entity {}", entity.0)
+        }
+    }
+    pub fn insert_graphic(&mut self, entity: Entity, graphic: Graphic) {
+        self.graphics.insert(entity, graphic);
+        self.changed.push(entity);
+    }
+    pub fn remove_graphic(&mut self, entity: Entity) -> Option<Graphic> {
+        self.changed.push(entity);
+        self.graphics.remove(entity)
+    }
+    pub fn get_graphic(&self, entity: Entity) -> Option<&Graphic> {
+        self.graphics.get(entity)
+    }
+    pub fn insert_light(&mut self, entity: Entity, light: SceneLight) {
+        self.lights.insert(entity, light);
+        self.changed.push(entity);
+    }
+    pub fn remove_light(&mut self, entity: Entity) -> Option<SceneLight> {
+        self.changed.push(entity);
+        self.lights.remove(entity)
+    }
+    pub fn get_light(&self, entity: Entity) -> Option<&SceneLight> {
+        self.lights.get(entity)
+    }
+    /// Steers an entity along paths on the navigation mesh, unless it also has a rigid body
+    /// takes   entity as Entity
+    ///         agent as NavAgent
+    pub fn insert_agent(&mut self, entity: Entity, agent: NavAgent) {
+        self.agents.insert(entity, agent);
+    }
+    pub fn remove_agent(&mut self, entity: Entity) -> Option<NavAgent> {
+        self.agents.remove(entity)
+    }
+    pub fn get_agent(&self, entity: Entity) -> Option<&NavAgent> {
+        self.agents.get(entity)
+    }
+    pub fn get_agent_mut(&mut self, entity: Entity) -> Option<&mut NavAgent> {
+        self.agents.get_mut(entity)
+    }
+    pub fn get_agents(&self) -> &ComponentStore<NavAgent> {
+        &self.agents
+    }
+    /// Gets an agent along with the transform it moves, for calls needing both
+    /// takes   entity as Entity
+    /// returns agent and transform for entities with an agent as Option<(&mut NavAgent, &mut Transform)>
+    pub fn get_agent_and_transform_mut(&mut self, entity: Entity) -> Option<(&mut NavAgent, &mut Transform)> {
+        Some((self.agents.get_mut(entity)?, self.transforms.get_mut(entity.0)?))
+    }
+    /// Hands an entity's movement over to rigid body physics, any agent it has stops steering it
+    /// takes   entity as Entity
+    ///         rigid body as RigidBody
+    pub fn insert_rigid_body(&mut self, entity: Entity, rigid_body: RigidBody) {
+        self.rigid_bodies.insert(entity, rigid_body);
+    }
+    pub fn remove_rigid_body(&mut self, entity: Entity) -> Option<RigidBody> {
+        self.rigid_bodies.remove(entity)
+    }
+    pub fn get_rigid_body(&self, entity: Entity) -> Option<&RigidBody> {
+        self.rigid_bodies.get(entity)
+    }
+    pub fn get_rigid_bodies(&self) -> &ComponentStore<RigidBody> {
+        &self.rigid_bodies
+    }
+    /// Pushes an entity's rigid body, waking it if it was resting
+    /// takes   entity as Entity
+    ///         impulse as [f32; 3]
+    ///         point the impulse acts at as [f32; 3]
+    pub fn apply_impulse(&mut self, entity: Entity, impulse: [f32; 3], point: [f32; 3]) {
+        match self.rigid_bodies.get_mut(entity) {
+            Some(rigid_body) => rigid_body.apply_impulse(&self.transforms[entity.0], impulse, point),
+            None => panic!("Could not apply impulse to entity {} without a rigid body", entity.0)
+        }
+    }
+    /// Reads the value rolled by a rigid body die
+    /// takes   entity as Entity
+    ///         physics settings as PhysicsSettings
+    /// returns rolling, the face value pointing up or cocked, for dice with face values, as Option<RollResult>
+    pub fn get_roll(&self, entity: Entity, physics: &PhysicsSettings) -> Option<RollResult> {
+        self.rigid_bodies.get(entity)?.get_roll(&self.transforms[entity.0], physics)
+    }
+    /// Gets the scene node the render system draws an entity with
+    /// takes   entity as Entity
+    /// returns node once rendered for entities with a graphic or light as Option<NodeId>
+    pub fn get_node(&self, entity: Entity) -> Option<NodeId> {
+        self.nodes.get(entity).copied()
+    }
+    /// Remembers every transform as it was before the tick, to draw between ticks
+    pub fn begin_tick(&mut self) {
+        self.previous_transforms.copy_from_slice(&self.transforms);
+    }
+}
+
+/// Steers every entity with an agent and no rigid body by a time step, avoiding the others as they
+/// were at the start of the step whatever order they move in, rigid bodies are seen but never avoid
+/// takes   entities as Entities
+///         navigation mesh as NavMesh
+///         physics settings as PhysicsSettings
+///         random number generator as Rng
+///         time step in seconds as f32
+pub fn movement_system<R: Rng>(entities: &mut Entities, nav_mesh: &NavMesh, physics: &PhysicsSettings, rng: &mut R, time_step: f32) {
+    let Entities { transforms, agents, rigid_bodies, .. } = entities;
+    let (indices, snapshot): (Vec<usize>, Vec<SteeringAgent>) = (0..transforms.len())
+        .filter_map(|index| {
+            let agent = match (agents.get(Entity(index)), rigid_bodies.get(Entity(index))) {
+                (_, Some(rigid_body)) => SteeringAgent {
+                    entity: Entity(index),
+                    position: transforms[index].get_cgmath_position(),
+                    velocity: rigid_body.get_linear_velocity().into(),
+                    radius: rigid_body.get_radius(),
+                    avoiding: false
+                },
+                (Some(agent), None) => agent.get_steering_agent(Entity(index), &transforms[index]),
+                (None, None) => return None
+            };
+            Some((index, agent))
+        })
+        .unzip();
+    for (slot, &index) in indices.iter().enumerate() {
+        if rigid_bodies.contains(Entity(index)) {
+            continue;
+        }
+        if let Some(agent) = agents.get_mut(Entity(index)) {
+            agent.update_time_step(&mut transforms[index], nav_mesh, physics, &snapshot, slot, rng, time_step);
+        }
+    }
+}
+
+/// Steps every rigid body by a time step, then resolves contacts between those whose boxes overlap
+/// takes   entities as Entities
+///         physics settings as PhysicsSettings
+///         time step in seconds as f32
+pub fn physics_system(entities: &mut Entities, physics: &PhysicsSettings, time_step: f32) {
+    let Entities { transforms, rigid_bodies, .. } = entities;
+    for (entity, rigid_body) in rigid_bodies.iter_mut() {
+        rigid_body.step(&mut transforms[entity.0], physics, time_step);
+    }
+    let (indices, bounds): (Vec<usize>, Vec<Bounds>) = rigid_bodies.iter()
+        .map(|(entity, rigid_body)| (entity.0, rigid_body.get_bounds(&transforms[entity.0])))
+        .unzip();
+    for (a, b) in overlapping_pairs(&bounds) {
+        let (a, b) = (indices[a], indices[b]);
+        let (a_body, b_body) = pair_mut(&mut rigid_bodies.components, a, b);
+        let (a_transform, b_transform) = pair_mut(transforms, a, b);
+        if let (Some(a_body), Some(b_body)) = (a_body, b_body) {
+            collide(a_body, a_transform, b_body, b_transform, physics, time_step);
+        }
+    }
+}
+
+// borrows two different elements at once, the first index being the lower
+fn pair_mut<T>(slice: &mut [T], a: usize, b: usize) -> (&mut T, &mut T) {
+    let (first, second) = slice.split_at_mut(b);
+    (&mut first[a], &mut second[0])
+}
+
+/// Places a scene node for every entity with a graphic or light where it is drawn between the last
+/// two ticks, making nodes for new entities and again for those whose graphic or light changed,
+/// then updates the scene
+/// takes   entities as Entities
+///         scene as Scene
+///         parent node of the entity nodes as Option<NodeId>
+///         fraction of a tick passed since the latest tick as f32
+pub fn render_system(entities: &mut Entities, scene: &mut Scene, parent: Option<NodeId>, alpha: f32) {
+    for entity in entities.changed.drain(..) {
+        if let Some(node) = entities.nodes.remove(entity) {
+            scene.remove_node(node);
+        }
+    }
+    for index in 0..entities.transforms.len() {
+        let entity = Entity(index);
+        let transform = entities.previous_transforms[index].interpolate(&entities.transforms[index], alpha);
+        if let Some(&node) = entities.nodes.get(entity) {
+            scene.set_local(node, transform);
+            continue;
+        }
+        let (graphic, light) = (entities.graphics.get(entity), entities.lights.get(entity));
+        if graphic.is_none() && light.is_none() {
+            continue;
+        }
+        let node = scene.add_node(parent, transform);
+        if let Some(graphic) = graphic {
+            scene.attach_graphic(node, graphic.clone());
+        }
+        if let Some(light) = light {
+            scene.attach_light(node, *light);
+        }
+        entities.nodes.insert(entity, node);
+    }
+    scene.update();
+}
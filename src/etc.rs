@@ -10,7 +10,7 @@ use std::{
 /// returns file contents as vec<u8>
 pub fn load_bytes(file_path: &str) -> Vec<u8> {
     let path = Path::new(file_path);
-    let mut file = match File::open(&path) {
+    let mut file = match File::open(path) {
         Err(why) => panic!("Couldn't open {}: {}", path.display(), why),
         Ok(file) => file,
     };
@@ -37,21 +37,98 @@ pub fn build_program(display: &glium::Display, vertex_shader_file_path: &str, fr
 /// Constructs perspective transfromation matrix
 /// takes   drawing surface as glium::Frame
 /// returns perspective transformation as [[f32;4];4]
+#[allow(dead_code)]
 pub fn perspective_matrix(target: &glium::Frame) -> [[f32; 4]; 4] {
     let (width, height) = target.get_dimensions();
-    let aspect_ratio = height as f32 / width as f32;
+    perspective_projection(width as f32, height as f32, std::f32::consts::FRAC_PI_3, 0.1, 1024.0)
+}
 
-    let fov: f32 = 3.141592 / 3.0;
-    let zfar = 1024.0;
-    let znear = 0.1;
+/// Constructs perspective transformation matrix from a viewport size
+/// takes   viewport width and height as f32
+///         vertical field of view in radians as f32
+///         near and far plane distances as f32
+/// returns perspective transformation as [[f32;4];4]
+#[allow(dead_code)]
+pub fn perspective_projection(width: f32, height: f32, fov: f32, znear: f32, zfar: f32) -> [[f32; 4]; 4] {
+    let top = znear * (fov / 2.0).tan();
+    let right = top * width / height;
+    off_centre_projection(-right, right, -top, top, znear, zfar)
+}
+
+/// Constructs asymmetric perspective transformation matrix from the extent of the near plane
+/// takes   left, right, bottom and top of the near plane as f32
+///         near and far plane distances as f32
+/// returns perspective transformation as [[f32;4];4]
+#[allow(dead_code)]
+pub fn off_centre_projection(left: f32, right: f32, bottom: f32, top: f32, znear: f32, zfar: f32) -> [[f32; 4]; 4] {
+    // view space looks down +z so the centre offset is subtracted as depth grows
+    [
+        [2.0 * znear / (right - left)      ,              0.0                 ,               0.0               ,   0.0],
+        [             0.0                  ,   2.0 * znear / (top - bottom)   ,               0.0               ,   0.0],
+        [-(right + left) / (right - left)  , -(top + bottom) / (top - bottom) ,   (zfar+znear)/(zfar-znear)     ,   1.0],
+        [             0.0                  ,              0.0                 , -(2.0*zfar*znear)/(zfar-znear)  ,   0.0],
+    ]
+}
 
+/// Constructs perspective transformation matrix with the far plane at infinity
+/// takes   viewport width and height as f32
+///         vertical field of view in radians as f32
+///         near plane distance as f32
+/// returns perspective transformation as [[f32;4];4]
+#[allow(dead_code)]
+pub fn infinite_projection(width: f32, height: f32, fov: f32, znear: f32) -> [[f32; 4]; 4] {
     let f = 1.0 / (fov / 2.0).tan();
+    [
+        [f * height / width ,    0.0,        0.0     ,   0.0],
+        [        0.0        ,     f ,        0.0     ,   0.0],
+        [        0.0        ,    0.0,        1.0     ,   1.0],
+        [        0.0        ,    0.0, -2.0 * znear   ,   0.0],
+    ]
+}
 
+/// Constructs perspective transformation matrix for a zero to one clip range, mapping the near
+/// plane to depth 1 and the far plane to depth 0, draw with DepthTest::IfMore and clear depth
+/// to 0 so far away surfaces keep their precision. The zero to one range needs glClipControl,
+/// which glium 0.26 cannot switch on, so no camera draws with this yet
+/// takes   viewport width and height as f32
+///         vertical field of view in radians as f32
+///         near and far plane distances as f32
+/// returns perspective transformation as [[f32;4];4]
+#[allow(dead_code)]
+pub fn reversed_z_projection(width: f32, height: f32, fov: f32, znear: f32, zfar: f32) -> [[f32; 4]; 4] {
+    let f = 1.0 / (fov / 2.0).tan();
+    // w is the view space depth, so depth falls from one at the near plane as z grows
     [
-        [f *   aspect_ratio   ,    0.0,              0.0              ,   0.0],
-        [         0.0         ,     f ,              0.0              ,   0.0],
-        [         0.0         ,    0.0,  (zfar+znear)/(zfar-znear)    ,   1.0],
-        [         0.0         ,    0.0, -(2.0*zfar*znear)/(zfar-znear),   0.0],
+        [f * height / width ,    0.0,               0.0                 ,   0.0],
+        [        0.0        ,     f ,               0.0                 ,   0.0],
+        [        0.0        ,    0.0,      -znear / (zfar - znear)      ,   1.0],
+        [        0.0        ,    0.0,  znear * zfar / (zfar - znear)    ,   0.0],
+    ]
+}
+
+/// Constructs orthographic transformation matrix from a viewport size
+/// takes   viewport width and height as f32
+///         height of the visible area in world units as f32
+///         near and far plane distances as f32
+/// returns orthographic transformation as [[f32;4];4]
+#[allow(dead_code)]
+pub fn orthographic_projection(width: f32, height: f32, view_height: f32, znear: f32, zfar: f32) -> [[f32; 4]; 4] {
+    let top = view_height / 2.0;
+    let right = top * width / height;
+    off_centre_orthographic_projection(-right, right, -top, top, znear, zfar)
+}
+
+/// Constructs orthographic transformation matrix from the extent of the visible box
+/// takes   left, right, bottom and top of the visible area as f32
+///         near and far plane distances as f32
+/// returns orthographic transformation as [[f32;4];4]
+#[allow(dead_code)]
+pub fn off_centre_orthographic_projection(left: f32, right: f32, bottom: f32, top: f32, znear: f32, zfar: f32) -> [[f32; 4]; 4] {
+    [
+        [2.0 / (right - left)              ,              0.0                 ,          0.0          ,   0.0],
+        [             0.0                  ,       2.0 / (top - bottom)       ,          0.0          ,   0.0],
+        [             0.0                  ,              0.0                 , 2.0 / (zfar - znear)  ,   0.0],
+        [-(right + left) / (right - left)  , -(top + bottom) / (top - bottom) , -(zfar + znear) / (zfar - znear), 1.0],
     ]
 }
 
@@ -60,6 +137,7 @@ pub fn perspective_matrix(target: &glium::Frame) -> [[f32; 4]; 4] {
 ///         direction vector as [f32; 3],
 ///         up vector as [f32; 3]
 /// returns veiew transformation as [[f32; 4]; 4]
+#[allow(dead_code)]
 pub fn view_matrix(position: &[f32; 3], direction: &[f32; 3], up: &[f32; 3]) -> [[f32; 4]; 4] {
     let f = {
         let f = direction;
@@ -92,4 +170,61 @@ pub fn view_matrix(position: &[f32; 3], direction: &[f32; 3], up: &[f32; 3]) ->
         [s_norm[2], u[2], f[2], 0.0],
         [p[0], p[1], p[2], 1.0],
     ]
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // depth of a view space point along +z after the perspective divide
+    fn ndc_depth(projection: [[f32; 4]; 4], z: f32) -> f32 {
+        let clip_z = projection[2][2] * z + projection[3][2];
+        let clip_w = projection[2][3] * z + projection[3][3];
+        clip_z / clip_w
+    }
+
+    fn assert_depth(projection: [[f32; 4]; 4], z: f32, expected: f32) {
+        let depth = ndc_depth(projection, z);
+        assert!((depth - expected).abs() < 1e-4, "depth at {} was {}, expected {}", z, depth, expected);
+    }
+
+    #[test]
+    fn perspective_maps_near_and_far_to_minus_one_and_one() {
+        let projection = perspective_projection(1280.0, 720.0, std::f32::consts::FRAC_PI_3, 0.1, 1024.0);
+        assert_depth(projection, 0.1, -1.0);
+        assert_depth(projection, 1024.0, 1.0);
+    }
+
+    #[test]
+    fn off_centre_maps_near_and_far_to_minus_one_and_one() {
+        let projection = off_centre_projection(-0.2, 0.1, -0.05, 0.15, 0.5, 50.0);
+        assert_depth(projection, 0.5, -1.0);
+        assert_depth(projection, 50.0, 1.0);
+    }
+
+    #[test]
+    fn infinite_maps_near_to_minus_one_and_approaches_one() {
+        let projection = infinite_projection(1280.0, 720.0, std::f32::consts::FRAC_PI_3, 0.1);
+        assert_depth(projection, 0.1, -1.0);
+        assert_depth(projection, 1.0e6, 1.0);
+        assert!(ndc_depth(projection, 1.0e6) < 1.0);
+    }
+
+    #[test]
+    fn reversed_z_maps_near_and_far_to_one_and_zero() {
+        let projection = reversed_z_projection(1280.0, 720.0, std::f32::consts::FRAC_PI_3, 0.1, 1024.0);
+        assert_depth(projection, 0.1, 1.0);
+        assert_depth(projection, 1024.0, 0.0);
+        assert!(ndc_depth(projection, 1.0) > ndc_depth(projection, 2.0));
+    }
+
+    #[test]
+    fn orthographic_maps_near_and_far_to_minus_one_and_one() {
+        let projection = orthographic_projection(1280.0, 720.0, 3.0, 0.1, 16.0);
+        assert_depth(projection, 0.1, -1.0);
+        assert_depth(projection, 16.0, 1.0);
+        let projection = off_centre_orthographic_projection(-2.0, 1.0, -1.0, 3.0, 1.0, 9.0);
+        assert_depth(projection, 1.0, -1.0);
+        assert_depth(projection, 9.0, 1.0);
+    }
+}
@@ -1,13 +1,30 @@
 use glium::{
     IndexBuffer,
+    index::PrimitiveType,
+    Rect,
     Surface,
+    texture::{
+        CompressedFormat,
+        CompressedMipmapsOption,
+        CompressedSrgbFormat,
+        MipmapsOption
+    },
     uniform,
+    uniforms::{
+        AsUniformValue,
+        MagnifySamplerFilter,
+        MinifySamplerFilter,
+        SamplerBehavior,
+        SamplerWrapFunction,
+        UniformValue
+    },
     VertexBuffer
 };
 use std::{
     collections::HashMap,
     fs,
     io::Cursor,
+    rc::Rc,
     string::String
 };
 use obj::{
@@ -15,24 +32,240 @@ use obj::{
     TexturedVertex
 };
 use cgmath::{
-    Quaternion,
-    Matrix4
+    InnerSpace,
+    Matrix4,
+    SquareMatrix,
+    Vector3
 };
 
-use crate::etc::load_bytes;
+use crate::{
+    atlas::AtlasBuilder,
+    compressed::{
+        BlockFormat,
+        CompressedImage
+    },
+    debug::{
+        self,
+        DebugRenderer,
+        DebugVertex
+    },
+    etc::load_bytes,
+    lod,
+    pick::Ray,
+    transform::Transform
+};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BlendMode {
+    Opaque,
+    AlphaBlended,
+    Additive
+}
+impl BlendMode {
+    pub fn from_name(name: &str) -> BlendMode {
+        match name {
+            "opaque" => BlendMode::Opaque,
+            "alpha" => BlendMode::AlphaBlended,
+            "additive" => BlendMode::Additive,
+            _ => panic!("Could not recognize the blend mode '{}'", name)
+        }
+    }
+    fn get_blend(&self) -> glium::Blend {
+        match self {
+            BlendMode::Opaque => glium::Blend::default(),
+            BlendMode::AlphaBlended => glium::Blend::alpha_blending(),
+            BlendMode::Additive => {
+                let addition = glium::BlendingFunction::Addition {
+                    source: glium::LinearBlendingFactor::SourceAlpha,
+                    destination: glium::LinearBlendingFactor::One
+                };
+                glium::Blend {
+                    color: addition,
+                    alpha: addition,
+                    constant_value: (0.0, 0.0, 0.0, 0.0)
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GraphicSampler {
+    minify_filter: MinifySamplerFilter,
+    magnify_filter: MagnifySamplerFilter,
+    anisotropy: u16,
+    wrap_function: SamplerWrapFunction
+}
+impl GraphicSampler {
+    pub fn new() -> GraphicSampler {
+        GraphicSampler {
+            minify_filter: MinifySamplerFilter::LinearMipmapLinear,
+            magnify_filter: MagnifySamplerFilter::Linear,
+            anisotropy: 1,
+            wrap_function: SamplerWrapFunction::Repeat
+        }
+    }
+    /// Reads sampler settings from a manifest, missing keys keep their defaults
+    /// takes   sampler section of a manifest as serde_json::Value
+    /// returns sampler settings as GraphicSampler
+    pub fn from_json(value: &serde_json::Value) -> GraphicSampler {
+        let mut sampler = GraphicSampler::new();
+        if let Some(name) = value["min_filter"].as_str() {
+            sampler.minify_filter = match name {
+                "nearest" => MinifySamplerFilter::Nearest,
+                "linear" => MinifySamplerFilter::Linear,
+                "nearest_mipmap_nearest" => MinifySamplerFilter::NearestMipmapNearest,
+                "linear_mipmap_nearest" => MinifySamplerFilter::LinearMipmapNearest,
+                "nearest_mipmap_linear" => MinifySamplerFilter::NearestMipmapLinear,
+                "linear_mipmap_linear" => MinifySamplerFilter::LinearMipmapLinear,
+                _ => panic!("Could not recognize the minify filter '{}'", name)
+            };
+        }
+        if let Some(name) = value["mag_filter"].as_str() {
+            sampler.magnify_filter = match name {
+                "nearest" => MagnifySamplerFilter::Nearest,
+                "linear" => MagnifySamplerFilter::Linear,
+                _ => panic!("Could not recognize the magnify filter '{}'", name)
+            };
+        }
+        if let Some(level) = value["anisotropy"].as_u64() {
+            sampler.anisotropy = level.max(1).min(u16::MAX as u64) as u16;
+        }
+        if let Some(name) = value["wrap"].as_str() {
+            sampler.wrap_function = match name {
+                "repeat" => SamplerWrapFunction::Repeat,
+                "mirror" => SamplerWrapFunction::Mirror,
+                "clamp" => SamplerWrapFunction::Clamp,
+                "mirror_clamp" => SamplerWrapFunction::MirrorClamp,
+                _ => panic!("Could not recognize the wrap function '{}'", name)
+            };
+        }
+        sampler
+    }
+    fn get_behavior(&self) -> SamplerBehavior {
+        SamplerBehavior {
+            minify_filter: self.minify_filter,
+            magnify_filter: self.magnify_filter,
+            max_anisotropy: self.anisotropy,
+            wrap_function: (self.wrap_function, self.wrap_function, self.wrap_function),
+            .. Default::default()
+        }
+    }
+}
+
+// binds either kind of texture through the same uniform
+struct SampledTexture<'a>(UniformValue<'a>);
+impl<'a> AsUniformValue for SampledTexture<'a> {
+    fn as_uniform_value(&self) -> UniformValue<'_> {
+        self.0
+    }
+}
+
+#[derive(Debug)]
+enum DiffuseTexture {
+    Uncompressed(glium::texture::SrgbTexture2d),
+    Compressed(glium::texture::CompressedSrgbTexture2d),
+    Atlas(Rc<glium::texture::SrgbTexture2d>)
+}
+impl DiffuseTexture {
+    fn sampled(&self, behavior: SamplerBehavior) -> SampledTexture<'_> {
+        match self {
+            DiffuseTexture::Uncompressed(texture) => SampledTexture(UniformValue::SrgbTexture2d(texture, Some(behavior))),
+            DiffuseTexture::Compressed(texture) => SampledTexture(UniformValue::CompressedSrgbTexture2d(texture, Some(behavior))),
+            DiffuseTexture::Atlas(texture) => SampledTexture(UniformValue::SrgbTexture2d(texture, Some(behavior)))
+        }
+    }
+}
+
+#[derive(Debug)]
+enum NormalsTexture {
+    Uncompressed(glium::texture::Texture2d),
+    Compressed(glium::texture::CompressedTexture2d),
+    Atlas(Rc<glium::texture::Texture2d>)
+}
+impl NormalsTexture {
+    fn sampled(&self, behavior: SamplerBehavior) -> SampledTexture<'_> {
+        match self {
+            NormalsTexture::Uncompressed(texture) => SampledTexture(UniformValue::Texture2d(texture, Some(behavior))),
+            NormalsTexture::Compressed(texture) => SampledTexture(UniformValue::CompressedTexture2d(texture, Some(behavior))),
+            NormalsTexture::Atlas(texture) => SampledTexture(UniformValue::Texture2d(texture, Some(behavior)))
+        }
+    }
+}
+
+// textures waiting for every manifest to load before being packed into atlas pages
+#[derive(Debug)]
+struct AtlasRequest {
+    texture_key: String,
+    diffuse: image::RgbaImage,
+    normals: image::RgbaImage,
+    sampler: GraphicSampler,
+    blend_mode: BlendMode,
+    opacity: f32
+}
+
+const ATLAS_PAGE_SIZE: u32 = 4096;
+const ATLAS_PADDING: u32 = 8;
 
 #[derive(Debug)]
 pub struct GraphicTexture {
-    diffuse_tex: glium::texture::SrgbTexture2d,
-    normals_map: glium::texture::Texture2d
+    diffuse_tex: DiffuseTexture,
+    normals_map: NormalsTexture,
+    // compressed containers keep their rows top down and bc5 normals only store x and y
+    diffuse_flip: bool,
+    normals_flip: bool,
+    normals_xy: bool,
+    // atlas page and the uv offset and scale of this texture within it
+    atlas: Option<(usize, [f32; 4])>,
+    sampler: GraphicSampler,
+    blend_mode: BlendMode,
+    opacity: f32
 }
 impl GraphicTexture {
-    pub fn from_path(display: &glium::Display, diffuse_file_path: &str, normal_file_path: &str) -> GraphicTexture {
+    pub fn from_path(display: &glium::Display, diffuse_file_path: &str, normal_file_path: &str,
+                     sampler: GraphicSampler, blend_mode: BlendMode, opacity: f32) -> GraphicTexture {
+        let (diffuse_tex, diffuse_flip) = if CompressedImage::is_container(diffuse_file_path) {
+            GraphicTexture::load_compressed_diffuse_tex(display, diffuse_file_path)
+        } else {
+            (DiffuseTexture::Uncompressed(GraphicTexture::load_diffuse_tex(display, diffuse_file_path)), false)
+        };
+        let (normals_map, normals_flip, normals_xy) = if CompressedImage::is_container(normal_file_path) {
+            GraphicTexture::load_compressed_normals_map(display, normal_file_path)
+        } else {
+            (NormalsTexture::Uncompressed(GraphicTexture::load_normals_map(display, normal_file_path)), false, false)
+        };
+        GraphicTexture {
+            diffuse_tex,
+            normals_map,
+            diffuse_flip,
+            normals_flip,
+            normals_xy,
+            atlas: None,
+            sampler,
+            blend_mode,
+            opacity
+        }
+    }
+    fn from_images(display: &glium::Display, diffuse: image::RgbaImage, normals: image::RgbaImage,
+                   sampler: GraphicSampler, blend_mode: BlendMode, opacity: f32) -> GraphicTexture {
         GraphicTexture {
-            diffuse_tex: GraphicTexture::load_diffuse_tex(display, diffuse_file_path),
-            normals_map: GraphicTexture::load_normals_map(display, normal_file_path)
+            diffuse_tex: DiffuseTexture::Uncompressed(GraphicTexture::upload_diffuse_tex(display, diffuse)),
+            normals_map: NormalsTexture::Uncompressed(GraphicTexture::upload_normals_map(display, normals)),
+            diffuse_flip: false,
+            normals_flip: false,
+            normals_xy: false,
+            atlas: None,
+            sampler,
+            blend_mode,
+            opacity
         }
     }
+    pub fn get_blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+    pub fn get_atlas_page(&self) -> Option<usize> {
+        self.atlas.map(|(page, _)| page)
+    }
     fn get_image_format(file_path: &str) -> image::ImageFormat {
         let path = std::path::Path::new(file_path);
         let extension = path.extension().and_then(std::ffi::OsStr::to_str);
@@ -44,78 +277,342 @@ impl GraphicTexture {
             _ => panic!("Could not recognize the image format of {}", path.display())
         }
     }
-    fn load_diffuse_tex(display: &glium::Display, file_path: &str) -> glium::texture::SrgbTexture2d {
+    fn load_image(file_path: &str) -> image::RgbaImage {
         let format = GraphicTexture::get_image_format(file_path);
         let buffer = load_bytes(file_path);
-        let image = image::load(Cursor::new(buffer),
-                                format).unwrap().to_rgba();
+        image::load(Cursor::new(buffer),
+                    format).unwrap().to_rgba()
+    }
+    fn load_diffuse_tex(display: &glium::Display, file_path: &str) -> glium::texture::SrgbTexture2d {
+        GraphicTexture::upload_diffuse_tex(display, GraphicTexture::load_image(file_path))
+    }
+    fn load_normals_map(display: &glium::Display, file_path: &str) -> glium::texture::Texture2d {
+        GraphicTexture::upload_normals_map(display, GraphicTexture::load_image(file_path))
+    }
+    fn upload_diffuse_tex(display: &glium::Display, image: image::RgbaImage) -> glium::texture::SrgbTexture2d {
         let image_dimensions = image.dimensions();
         let raw_image = glium::texture::RawImage2d::from_raw_rgba_reversed(&image.into_raw(), image_dimensions);
-        glium::texture::SrgbTexture2d::new(display, raw_image).unwrap()
+        glium::texture::SrgbTexture2d::with_mipmaps(display, raw_image, MipmapsOption::AutoGeneratedMipmaps).unwrap()
     }
-    fn load_normals_map(display: &glium::Display, file_path: &str) -> glium::texture::Texture2d {
-        let format = GraphicTexture::get_image_format(file_path);
-        let buffer = load_bytes(file_path);
-        let image = image::load(Cursor::new(buffer),
-                                format).unwrap().to_rgba();
+    fn upload_normals_map(display: &glium::Display, image: image::RgbaImage) -> glium::texture::Texture2d {
         let image_dimensions = image.dimensions();
         let raw_image = glium::texture::RawImage2d::from_raw_rgba_reversed(&image.into_raw(), image_dimensions);
-        glium::texture::Texture2d::new(display, raw_image).unwrap()
+        glium::texture::Texture2d::with_mipmaps(display, raw_image, MipmapsOption::AutoGeneratedMipmaps).unwrap()
+    }
+    /// Loads a bcn diffuse texture, decompressing it when the driver cannot sample it
+    /// takes   display as glium::Display
+    ///         dds or ktx2 file path as str
+    /// returns texture and whether its rows run top down as (DiffuseTexture, bool)
+    fn load_compressed_diffuse_tex(display: &glium::Display, file_path: &str) -> (DiffuseTexture, bool) {
+        let image = CompressedImage::from_bytes(&load_bytes(file_path), file_path);
+        let format = match image.get_format() {
+            BlockFormat::Bc1 => Some(CompressedSrgbFormat::S3tcDxt1Alpha),
+            BlockFormat::Bc3 => Some(CompressedSrgbFormat::S3tcDxt5Alpha),
+            BlockFormat::Bc7 => Some(CompressedSrgbFormat::Bptc),
+            BlockFormat::Bc5 => None
+        };
+        match format {
+            Some(format) if format.is_supported(display) => {
+                let (width, height) = image.get_dimensions();
+                let texture = glium::texture::CompressedSrgbTexture2d::with_compressed_data(display, image.get_level(0),
+                    width, height, format, CompressedMipmapsOption::EmptyMipmapsMax(image.get_level_count() - 1)).unwrap();
+                for level in 1..image.get_level_count() {
+                    let (width, height) = image.get_level_dimensions(level);
+                    let rect = Rect { left: 0, bottom: 0, width, height };
+                    texture.mipmap(level).unwrap()
+                        .write_compressed_data(rect, image.get_level(level), width, height, format).unwrap();
+                }
+                (DiffuseTexture::Compressed(texture), image.is_top_down())
+            },
+            _ => {
+                let (width, height) = image.get_dimensions();
+                let texture = glium::texture::SrgbTexture2d::empty_with_mipmaps(display,
+                    MipmapsOption::EmptyMipmapsMax(image.get_level_count() - 1), width, height).unwrap();
+                GraphicTexture::write_decompressed_levels(&image, |level, rect, raw_image| {
+                    texture.mipmap(level).unwrap().write(rect, raw_image)
+                });
+                (DiffuseTexture::Uncompressed(texture), false)
+            }
+        }
     }
+    /// Loads a bcn normal map, decompressing it when the driver cannot sample it
+    /// takes   display as glium::Display
+    ///         dds or ktx2 file path as str
+    /// returns texture, whether its rows run top down and whether it only stores x and y as (NormalsTexture, bool, bool)
+    fn load_compressed_normals_map(display: &glium::Display, file_path: &str) -> (NormalsTexture, bool, bool) {
+        let image = CompressedImage::from_bytes(&load_bytes(file_path), file_path);
+        let format = match image.get_format() {
+            BlockFormat::Bc1 => CompressedFormat::S3tcDxt1NoAlpha,
+            BlockFormat::Bc3 => CompressedFormat::S3tcDxt5Alpha,
+            BlockFormat::Bc5 => CompressedFormat::RgtcFormatUU,
+            BlockFormat::Bc7 => CompressedFormat::BptcUnorm4
+        };
+        if format.is_supported(display) {
+            let (width, height) = image.get_dimensions();
+            let texture = glium::texture::CompressedTexture2d::with_compressed_data(display, image.get_level(0),
+                width, height, format, CompressedMipmapsOption::EmptyMipmapsMax(image.get_level_count() - 1)).unwrap();
+            for level in 1..image.get_level_count() {
+                let (width, height) = image.get_level_dimensions(level);
+                let rect = Rect { left: 0, bottom: 0, width, height };
+                texture.mipmap(level).unwrap()
+                    .write_compressed_data(rect, image.get_level(level), width, height, format).unwrap();
+            }
+            (NormalsTexture::Compressed(texture), image.is_top_down(), image.get_format() == BlockFormat::Bc5)
+        } else {
+            let (width, height) = image.get_dimensions();
+            let texture = glium::texture::Texture2d::empty_with_mipmaps(display,
+                MipmapsOption::EmptyMipmapsMax(image.get_level_count() - 1), width, height).unwrap();
+            GraphicTexture::write_decompressed_levels(&image, |level, rect, raw_image| {
+                texture.mipmap(level).unwrap().write(rect, raw_image)
+            });
+            (NormalsTexture::Uncompressed(texture), false, false)
+        }
+    }
+    fn write_decompressed_levels<F>(image: &CompressedImage, mut write: F)
+            where F: FnMut(u32, Rect, glium::texture::RawImage2d<u8>) {
+        for level in 0..image.get_level_count() {
+            let (width, height) = image.get_level_dimensions(level);
+            let rect = Rect { left: 0, bottom: 0, width, height };
+            let raw_image = glium::texture::RawImage2d::from_raw_rgba(image.decompress_level(level), (width, height));
+            write(level, rect, raw_image);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GraphicLod {
+    vertices: VertexBuffer<TexturedVertex>,
+    indices: IndexBuffer<u16>,
+    screen_size: f32
 }
 
 #[derive(Debug)]
 pub struct GraphicObject {
     vertices: VertexBuffer<TexturedVertex>,
-    indices: IndexBuffer<u16>
+    indices: IndexBuffer<u16>,
+    lods: Vec<GraphicLod>,
+    normal_lines: VertexBuffer<DebugVertex>,
+    positions: Vec<Vector3<f32>>,
+    triangles: Vec<[usize; 3]>,
+    bounding_radius: f32
 }
+#[allow(dead_code)]
 impl GraphicObject {
-    pub fn from_path(display: &glium::Display, object_file_path: &str) -> GraphicObject {
-        let (vertices, indices) = GraphicObject::load_object_file(&display, object_file_path);
+    /// Loads a mesh and its coarser levels of detail
+    /// takes   display as glium::Display
+    ///         object file path as str
+    ///         manifest lods array, each entry holding an object_file_path or a simplify ratio
+    ///         along with the screen_size below which it is drawn, as serde_json::Value
+    /// returns object as GraphicObject
+    pub fn from_path(display: &glium::Display, object_file_path: &str, lods: &serde_json::Value) -> GraphicObject {
+        let obj = GraphicObject::load_object_file(object_file_path);
+        let mut lods: Vec<GraphicLod> = lods.as_array().map_or(Vec::new(), |lods| lods.iter()
+            .map(|entry| {
+                let (vertices, indices) = match entry["object_file_path"].as_str() {
+                    Some(path) => {
+                        let lod_obj = GraphicObject::load_object_file(path);
+                        (lod_obj.vertices, lod_obj.indices)
+                    },
+                    None => lod::simplify(&obj.vertices, &obj.indices, entry["simplify"].as_f64().unwrap_or(0.5) as f32)
+                };
+                GraphicLod {
+                    vertices: VertexBuffer::new(display, &vertices).unwrap(),
+                    indices: IndexBuffer::new(display, PrimitiveType::TrianglesList, &indices).unwrap(),
+                    screen_size: entry["screen_size"].as_f64().unwrap() as f32
+                }
+            })
+            .collect());
+        // coarser levels take over at smaller sizes
+        lods.sort_by(|a, b| b.screen_size.partial_cmp(&a.screen_size).unwrap_or(std::cmp::Ordering::Equal));
+        let positions: Vec<Vector3<f32>> = obj.vertices.iter()
+            .map(|vertex| Vector3::from(vertex.position))
+            .collect();
+        let triangles = obj.indices.chunks(3)
+            .map(|face| [face[0] as usize, face[1] as usize, face[2] as usize])
+            .collect();
+        let bounding_radius = positions.iter()
+            .map(|position| position.magnitude())
+            .fold(0.0, f32::max);
         GraphicObject {
-            vertices,
-            indices
+            vertices: obj.vertex_buffer(display).unwrap(),
+            indices: obj.index_buffer(display).unwrap(),
+            lods,
+            normal_lines: debug::normal_lines(display, &obj.vertices, bounding_radius),
+            positions,
+            triangles,
+            bounding_radius
         }
     }
 
-    fn load_object_file(display: &glium::Display, object_file_path: &str) -> (VertexBuffer<TexturedVertex>, IndexBuffer<u16>) {
+    fn load_object_file(object_file_path: &str) -> Obj<TexturedVertex> {
         let buffer = load_bytes(object_file_path);
-        let obj: Obj<TexturedVertex> = load_obj(&buffer[..]).unwrap();
-        let vertices = obj.vertex_buffer(display).unwrap();
-        let indices = obj.index_buffer(display).unwrap();
-        (vertices, indices)
+        load_obj(&buffer[..]).unwrap()
+    }
+    /// Intersects a model space ray against the mesh, bounding sphere first
+    /// takes   model space ray as Ray
+    /// returns nearest face index, model space hit point and ray distance as Option<(usize, Vector3<f32>, f32)>
+    pub fn intersect(&self, ray: &Ray) -> Option<(usize, Vector3<f32>, f32)> {
+        ray.intersect_sphere(Vector3::new(0.0, 0.0, 0.0), self.bounding_radius)?;
+        let mut nearest: Option<(usize, f32)> = None;
+        for (face, triangle) in self.triangles.iter().enumerate() {
+            let hit = ray.intersect_triangle(
+                self.positions[triangle[0]],
+                self.positions[triangle[1]],
+                self.positions[triangle[2]]);
+            if let Some(distance) = hit {
+                if nearest.is_none_or(|(_, nearest_distance)| distance < nearest_distance) {
+                    nearest = Some((face, distance));
+                }
+            }
+        }
+        nearest.map(|(face, distance)| (face, Vector3::from(ray.point_at(distance)), distance))
+    }
+    pub fn get_bounding_radius(&self) -> f32 {
+        self.bounding_radius
+    }
+    /// Lists the screen sizes below which each coarser level is drawn
+    /// returns thresholds from largest to smallest as Vec<f32>
+    pub fn get_lod_thresholds(&self) -> Vec<f32> {
+        self.lods.iter().map(|lod| lod.screen_size).collect()
+    }
+    /// Gets the buffers of a level of detail, level 0 being the full mesh
+    /// takes   level as usize
+    /// returns vertex and index buffers as (VertexBuffer<TexturedVertex>, IndexBuffer<u16>)
+    pub fn get_lod_buffers(&self, level: usize) -> (&VertexBuffer<TexturedVertex>, &IndexBuffer<u16>) {
+        match level {
+            0 => (&self.vertices, &self.indices),
+            _ => {
+                let lod = &self.lods[(level - 1).min(self.lods.len() - 1)];
+                (&lod.vertices, &lod.indices)
+            }
+        }
     }
 }
 
 #[derive(Debug)]
 pub struct GraphicLibrary {
     obj_dict: HashMap<String, GraphicObject>,
-    tex_dict: HashMap<String, GraphicTexture>
+    tex_dict: HashMap<String, GraphicTexture>,
+    atlas_requests: Vec<AtlasRequest>,
+    atlas_utilisation: Vec<f32>
 }
 
 impl GraphicLibrary {
     pub fn new() -> GraphicLibrary {
         GraphicLibrary {
             obj_dict: HashMap::new(),
-            tex_dict: HashMap::new()
+            tex_dict: HashMap::new(),
+            atlas_requests: Vec::new(),
+            atlas_utilisation: Vec::new()
         }
     }
     pub fn load(display: &glium::Display, file_path: &str) -> GraphicLibrary {
         let mut library = GraphicLibrary::new();
         library.load_path(display, file_path);
+        library.build_atlas(display);
         library
     }
     pub fn load_json(&mut self, display: &glium::Display, json_file_path: &str) {
         let buffer: serde_json::Value = serde_json::from_slice(&load_bytes(json_file_path)).unwrap();
+        // navigation mesh manifests share the folder
+        if buffer["object_key"].is_null() {
+            return;
+        }
         self.obj_dict.insert(
             buffer["object_key"].to_string().trim_matches('"').to_string(),
-            GraphicObject::from_path(display, buffer["object_file_path"].to_string().trim_matches('"'))
-        );
-        self.tex_dict.insert(
-            buffer["texture_key"].to_string().trim_matches('"').to_string(),
-            GraphicTexture::from_path(display, buffer["diffuse_file_path"].to_string().trim_matches('"'),
-                                    buffer["normal_file_path"].to_string().trim_matches('"'))
+            GraphicObject::from_path(display, buffer["object_file_path"].to_string().trim_matches('"'), &buffer["lods"])
         );
+        // materials are opaque unless the manifest says otherwise
+        let blend_mode = buffer["blend_mode"].as_str().map_or(BlendMode::Opaque, BlendMode::from_name);
+        let opacity = buffer["opacity"].as_f64().unwrap_or(1.0) as f32;
+        let sampler = GraphicSampler::from_json(&buffer["sampler"]);
+        let texture_key = buffer["texture_key"].to_string().trim_matches('"').to_string();
+        let diffuse_file_path = buffer["diffuse_file_path"].to_string().trim_matches('"').to_string();
+        let normal_file_path = buffer["normal_file_path"].to_string().trim_matches('"').to_string();
+        // block compressed textures stay on their own since pages are packed uncompressed
+        let atlasable = buffer["atlasable"].as_bool().unwrap_or(false)
+            && !CompressedImage::is_container(&diffuse_file_path)
+            && !CompressedImage::is_container(&normal_file_path);
+        if atlasable {
+            self.atlas_requests.push(AtlasRequest {
+                texture_key,
+                diffuse: GraphicTexture::load_image(&diffuse_file_path),
+                normals: GraphicTexture::load_image(&normal_file_path),
+                sampler,
+                blend_mode,
+                opacity
+            });
+        } else {
+            self.tex_dict.insert(
+                texture_key,
+                GraphicTexture::from_path(display, &diffuse_file_path, &normal_file_path,
+                                        sampler, blend_mode, opacity)
+            );
+        }
+    }
+    /// Packs every texture marked atlasable into shared pages, textures too large for a page
+    /// are uploaded on their own
+    /// takes   display as glium::Display
+    pub fn build_atlas(&mut self, display: &glium::Display) {
+        let mut requests = std::mem::take(&mut self.atlas_requests);
+        // tallest first packs shelves tighter
+        requests.sort_by_key(|request| std::cmp::Reverse(request.diffuse.height()));
+        let mut diffuse_builder = AtlasBuilder::new(ATLAS_PAGE_SIZE, ATLAS_PADDING);
+        let mut normals_builder = AtlasBuilder::new(ATLAS_PAGE_SIZE, ATLAS_PADDING);
+        let mut placed = Vec::new();
+        for request in requests {
+            let (width, height) = request.diffuse.dimensions();
+            let placement = match diffuse_builder.reserve(width, height) {
+                Some(placement) => placement,
+                None => {
+                    self.tex_dict.insert(request.texture_key, GraphicTexture::from_images(display,
+                        request.diffuse, request.normals, request.sampler, request.blend_mode, request.opacity));
+                    continue;
+                }
+            };
+            // both builders see the same sizes in the same order so their placements match
+            let normals_placement = normals_builder.reserve(width, height).unwrap();
+            let normals = if request.normals.dimensions() == (width, height) {
+                request.normals
+            } else {
+                image::imageops::resize(&request.normals, width, height, image::imageops::FilterType::Triangle)
+            };
+            diffuse_builder.write(&placement, &request.diffuse.into_raw());
+            normals_builder.write(&normals_placement, &normals.into_raw());
+            placed.push((request.texture_key, placement, request.sampler, request.blend_mode, request.opacity));
+        }
+        self.atlas_utilisation = diffuse_builder.get_utilisation();
+        let page_size = diffuse_builder.get_page_size();
+        let pages: Vec<(Rc<glium::texture::SrgbTexture2d>, Rc<glium::texture::Texture2d>)> = diffuse_builder.into_pages().into_iter()
+            .zip(normals_builder.into_pages())
+            .map(|(diffuse, normals)| {
+                let diffuse = glium::texture::RawImage2d::from_raw_rgba_reversed(&diffuse, (page_size, page_size));
+                let normals = glium::texture::RawImage2d::from_raw_rgba_reversed(&normals, (page_size, page_size));
+                (
+                    Rc::new(glium::texture::SrgbTexture2d::with_mipmaps(display, diffuse, MipmapsOption::AutoGeneratedMipmaps).unwrap()),
+                    Rc::new(glium::texture::Texture2d::with_mipmaps(display, normals, MipmapsOption::AutoGeneratedMipmaps).unwrap())
+                )
+            })
+            .collect();
+        for (texture_key, placement, sampler, blend_mode, opacity) in placed {
+            let (diffuse, normals) = &pages[placement.page];
+            self.tex_dict.insert(texture_key, GraphicTexture {
+                diffuse_tex: DiffuseTexture::Atlas(diffuse.clone()),
+                normals_map: NormalsTexture::Atlas(normals.clone()),
+                diffuse_flip: false,
+                normals_flip: false,
+                normals_xy: false,
+                atlas: Some((placement.page, placement.get_uv_transform(page_size))),
+                sampler,
+                blend_mode,
+                opacity
+            });
+        }
+    }
+    /// Reports how much of each atlas page holds texture data
+    /// returns fraction of each page covered as [f32]
+    pub fn get_atlas_utilisation(&self) -> &[f32] {
+        &self.atlas_utilisation
     }
     pub fn load_path(&mut self, display:&glium::Display, file_path: &str) {
         let paths = fs::read_dir(file_path).unwrap();
@@ -142,52 +639,223 @@ impl GraphicLibrary {
         }
     }
 
-    pub fn draw(&self, target: &mut glium::Frame, object_key: &str, texture_key: &str, translation: [f32;3], rotation: [f32;4], scaling: [f32;3],
-            view: [[f32;4]; 4], perspective: [[f32;4]; 4], u_light: [f32; 3], program: &glium::Program, params: &glium::DrawParameters) {
-    let t = Matrix4::from_translation(cgmath::Vector3::new(translation[0],translation[1],translation[2]));
-    let r = Matrix4::from(Quaternion::from(rotation));
-    let s = Matrix4::from_nonuniform_scale(-scaling[0], scaling[1], scaling[2]);
-    let m = t * r * s;
-    let model: [[f32;4];4] = m.into();
+    /// Intersects a world space ray against a placed object
+    /// takes   world space ray as Ray
+    ///         object key as str
+    ///         placement as Transform
+    /// returns face index, world space hit point and world distance as Option<(usize, [f32;3], f32)>
+    pub fn pick(&self, ray: &Ray, object_key: &str, transform: &Transform) -> Option<(usize, [f32;3], f32)> {
+        let model = transform.to_model_matrix();
+        let local_ray = ray.transformed(&model.invert()?);
+        let (face, point, _) = self.get_obj(object_key).intersect(&local_ray)?;
+        let world_point = (model * point.extend(1.0)).truncate();
+        let distance = (world_point - Vector3::from(ray.get_origin())).magnitude();
+        Some((face, world_point.into(), distance))
+    }
+
+    pub fn draw(&self, target: &mut glium::Frame, object_key: &str, texture_key: &str, lod: usize, transform: &Transform,
+            context: &DrawContext) {
+    let DrawContext { view, perspective, light: u_light, program, params, debug } = *context;
+    let model: [[f32;4];4] = transform.to_model_matrix().into();
     let object = self.get_obj(object_key);
     let texture = self.get_tex(texture_key);
-    target.draw(&object.vertices,
-        &object.indices,
-        &program,
+    let mode = debug.get_mode();
+    let (vertices, indices) = object.get_lod_buffers(lod);
+    let diffuse_tex = if mode.uv_checker {
+        SampledTexture(UniformValue::SrgbTexture2d(debug.get_checker(), Some(texture.sampler.get_behavior())))
+    } else {
+        texture.diffuse_tex.sampled(texture.sampler.get_behavior())
+    };
+    target.draw(vertices,
+        indices,
+        program,
         &uniform!{
             model: model,
             view: view,
             perspective: perspective,
             u_light: u_light,
-            diffuse_tex: &texture.diffuse_tex,
-            normals_tex: &texture.normals_map
+            u_opacity: texture.opacity,
+            u_diffuse_flip: texture.diffuse_flip,
+            u_normals_flip: texture.normals_flip,
+            u_normals_xy: texture.normals_xy,
+            u_atlased: texture.atlas.is_some(),
+            u_uv_transform: texture.atlas.map_or([0.0, 0.0, 1.0, 1.0], |(_, transform)| transform),
+            u_flat_normals: mode.flat_normals,
+            u_uv_checker: mode.uv_checker,
+            diffuse_tex: diffuse_tex,
+            normals_tex: texture.normals_map.sampled(texture.sampler.get_behavior())
         },
         params).unwrap();
+    if mode.normals {
+        debug.draw_lines(target, &object.normal_lines, model, view, perspective, params);
+    }
+    }
+    /// Draws queued items, opaque materials first then blended materials back to front
+    /// takes   drawing surface as glium::Frame
+    ///         queued items as RenderQueue, emptied once drawn
+    ///         camera, light, opaque draw parameters and debug renderer as DrawContext
+    /// returns number of draw calls issued as u32
+    pub fn draw_queue(&self, target: &mut glium::Frame, queue: &mut RenderQueue, context: &DrawContext) -> u32 {
+        let mode = context.debug.get_mode();
+        let params = &glium::DrawParameters {
+            polygon_mode: if mode.wireframe { glium::PolygonMode::Line } else { context.params.polygon_mode },
+            .. context.params.clone()
+        };
+        let context = &DrawContext { params, ..*context };
+        let view_matrix = Matrix4::from(context.view);
+        let mut opaque = Vec::new();
+        let mut transparent = Vec::new();
+        let mut draws = 0;
+        for item in queue.items.drain(..) {
+            let texture = self.get_tex(&item.texture_key);
+            if texture.get_blend_mode() == BlendMode::Opaque {
+                opaque.push((texture.get_atlas_page(), item));
+            } else {
+                let position = view_matrix * item.transform.get_cgmath_position().extend(1.0);
+                transparent.push((position.z, texture.get_blend_mode(), item));
+            }
+        }
+        // keep items sharing an atlas page or texture next to each other to save rebinding
+        opaque.sort_by(|a, b| (a.0, &a.1.texture_key, &a.1.object_key).cmp(&(b.0, &b.1.texture_key, &b.1.object_key)));
+        for (_, item) in opaque {
+            self.draw(target, &item.object_key, &item.texture_key, item.lod, &item.transform, context);
+            draws += if mode.normals { 2 } else { 1 };
+        }
+        // view space z grows away from the camera
+        transparent.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        for (_, blend_mode, item) in transparent {
+            let blended_params = glium::DrawParameters {
+                depth: glium::Depth {
+                    write: false,
+                    .. params.depth
+                },
+                blend: blend_mode.get_blend(),
+                .. params.clone()
+            };
+            self.draw(target, &item.object_key, &item.texture_key, item.lod, &item.transform,
+                &DrawContext { params: &blended_params, ..*context });
+            draws += if mode.normals { 2 } else { 1 };
+        }
+        draws
+    }
+}
+
+/// Camera, light and pipeline state shared by every draw of a frame
+#[derive(Copy, Clone)]
+pub struct DrawContext<'a> {
+    pub view: [[f32; 4]; 4],
+    pub perspective: [[f32; 4]; 4],
+    pub light: [f32; 3],
+    pub program: &'a glium::Program,
+    pub params: &'a glium::DrawParameters<'a>,
+    pub debug: &'a DebugRenderer
+}
+
+#[derive(Debug, Clone)]
+struct DrawItem {
+    object_key: String,
+    texture_key: String,
+    lod: usize,
+    transform: Transform
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderQueue {
+    items: Vec<DrawItem>,
+    view: [[f32; 4]; 4],
+    perspective: [[f32; 4]; 4]
+}
+#[allow(dead_code)]
+impl RenderQueue {
+    pub fn new() -> RenderQueue {
+        RenderQueue {
+            items: Vec::new(),
+            view: Matrix4::identity().into(),
+            perspective: Matrix4::identity().into()
+        }
+    }
+    /// Sets the camera items are queued for, used to pick their level of detail
+    /// takes   view and perspective transformations as [[f32;4]; 4]
+    pub fn set_camera(&mut self, view: [[f32;4]; 4], perspective: [[f32;4]; 4]) {
+        self.view = view;
+        self.perspective = perspective;
+    }
+    pub fn get_view(&self) -> [[f32;4]; 4] {
+        self.view
+    }
+    pub fn get_perspective(&self) -> [[f32;4]; 4] {
+        self.perspective
+    }
+    pub fn push(&mut self, object_key: &str, texture_key: &str, lod: usize, transform: Transform) {
+        self.items.push(DrawItem {
+            object_key: object_key.to_string(),
+            texture_key: texture_key.to_string(),
+            lod,
+            transform
+        });
+    }
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
     }
 }
 
+// fraction past a threshold before switching level, stops flicker at the boundary
+const LOD_HYSTERESIS: f32 = 0.1;
+
 #[derive(Debug,Clone)]
 pub struct Graphic {
-    scale: [f32; 3],
+    transform: Transform,
     object_key: String,
-    texture_key: String
+    texture_key: String,
+    lod: usize
 }
 #[allow(dead_code)]
 impl Graphic {
     pub fn new(
-        scale: [f32; 3],
+        transform: Transform,
         object_key: String,
         texture_key: String
     ) -> Graphic {
         Graphic {
-            scale,
+            transform,
             object_key,
-            texture_key
+            texture_key,
+            lod: 0
         }
     }
-    pub fn draw(&self, target: &mut glium::Frame, library: &GraphicLibrary, position: [f32;3], orientation: [f32;4], view: [[f32;4]; 4], perspective: [[f32;4]; 4],
-                u_light: [f32; 3],program: &glium::Program, params: &glium::DrawParameters) {
-        library.draw(target, &self.object_key, &self.texture_key, position, orientation, self.scale,
-                   view, perspective, u_light, program, params);
+    pub fn get_object_key(&self) -> &str {
+        &self.object_key
+    }
+    pub fn get_texture_key(&self) -> &str {
+        &self.texture_key
+    }
+    pub fn get_transform(&self) -> &Transform {
+        &self.transform
+    }
+    /// Intersects a world space ray against the graphic
+    /// takes   world space ray as Ray
+    ///         graphic library as GraphicLibrary
+    ///         transform of the owner as Transform
+    /// returns face index, world space hit point and world distance as Option<(usize, [f32;3], f32)>
+    pub fn pick(&self, ray: &Ray, library: &GraphicLibrary, parent: &Transform) -> Option<(usize, [f32;3], f32)> {
+        library.pick(ray, &self.object_key, &parent.compose(&self.transform))
+    }
+    pub fn get_lod(&self) -> usize {
+        self.lod
+    }
+    /// Queues the graphic at the level of detail matching its size on screen
+    /// takes   render queue as RenderQueue
+    ///         graphic library as GraphicLibrary
+    ///         transform of the owner as Transform
+    pub fn queue(&mut self, queue: &mut RenderQueue, library: &GraphicLibrary, parent: &Transform) {
+        let transform = parent.compose(&self.transform);
+        let object = library.get_obj(&self.object_key);
+        let scale = transform.get_scale().iter().map(|s| s.abs()).fold(0.0, f32::max);
+        let size = lod::screen_size(transform.get_position(), object.get_bounding_radius() * scale, queue.get_view(), queue.get_perspective());
+        self.lod = lod::select_lod(self.lod, size, &object.get_lod_thresholds(), LOD_HYSTERESIS);
+        queue.push(&self.object_key, &self.texture_key, self.lod, transform);
     }
 }
\ No newline at end of file
@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use crate::text::TextRenderer;
+
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum HudAnchor {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight
+}
+
+#[derive(Debug, Clone)]
+pub struct HudLabel {
+    text: String,
+    color: [f32; 4]
+}
+
+#[derive(Debug, Clone)]
+pub struct HudPanel {
+    anchor: HudAnchor,
+    offset: [f32; 2],
+    background: [f32; 4],
+    labels: Vec<HudLabel>
+}
+#[allow(dead_code)]
+impl HudPanel {
+    pub fn new(anchor: HudAnchor, offset: [f32; 2], background: [f32; 4]) -> HudPanel {
+        HudPanel {
+            anchor,
+            offset,
+            background,
+            labels: Vec::new()
+        }
+    }
+    /// Sets the text of a label, adding labels up to the given line if needed
+    /// takes   line index as usize
+    ///         text as String
+    ///         color as [f32; 4]
+    pub fn set_label(&mut self, line: usize, text: String, color: [f32; 4]) {
+        while self.labels.len() <= line {
+            self.labels.push(HudLabel { text: String::new(), color });
+        }
+        self.labels[line] = HudLabel { text, color };
+    }
+    pub fn clear_labels(&mut self) {
+        self.labels.clear();
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Hud {
+    scale: f32,
+    padding: f32,
+    panels: Vec<HudPanel>
+}
+#[allow(dead_code)]
+impl Hud {
+    pub fn new(scale: f32, padding: f32) -> Hud {
+        Hud {
+            scale,
+            padding,
+            panels: Vec::new()
+        }
+    }
+    pub fn add_panel(&mut self, panel: HudPanel) -> usize {
+        self.panels.push(panel);
+        self.panels.len() - 1
+    }
+    pub fn get_panel_mut(&mut self, index: usize) -> &mut HudPanel {
+        match self.panels.get_mut(index) {
+            Some(panel) => panel,
+            None => panic!("Could not find panel {} in hud", index)
+        }
+    }
+    /// Queues every panel for drawing
+    /// takes   text renderer as TextRenderer
+    ///         window size in pixels as [f32; 2]
+    pub fn queue(&self, renderer: &mut TextRenderer, screen: [f32; 2]) {
+        let line_height = renderer.text_size("", self.scale)[1] + self.scale * 2.0;
+        for panel in &self.panels {
+            if panel.labels.is_empty() {
+                continue;
+            }
+            // panel extent from its widest label and number of lines
+            let width = panel.labels.iter()
+                .map(|label| renderer.text_size(&label.text, self.scale)[0])
+                .fold(0.0, f32::max) + self.padding * 2.0;
+            let height = line_height * panel.labels.len() as f32 - self.scale * 2.0 + self.padding * 2.0;
+            let x = match panel.anchor {
+                HudAnchor::TopLeft | HudAnchor::BottomLeft => panel.offset[0],
+                HudAnchor::TopRight | HudAnchor::BottomRight => screen[0] - width - panel.offset[0]
+            };
+            let y = match panel.anchor {
+                HudAnchor::TopLeft | HudAnchor::TopRight => panel.offset[1],
+                HudAnchor::BottomLeft | HudAnchor::BottomRight => screen[1] - height - panel.offset[1]
+            };
+            if panel.background[3] > 0.0 {
+                renderer.queue_rect([x, y], [width, height], panel.background);
+            }
+            for (line, label) in panel.labels.iter().enumerate() {
+                renderer.queue_text(
+                    &label.text,
+                    [x + self.padding, y + self.padding + line_height * line as f32],
+                    self.scale,
+                    label.color);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FrameStats {
+    fps: f32,
+    tick_time: Duration,
+    draws: u32,
+    frames: u32,
+    frame_time: Duration,
+    ticks: u32,
+    tick_total: Duration
+}
+#[allow(dead_code)]
+impl FrameStats {
+    pub fn new() -> FrameStats {
+        FrameStats {
+            fps: 0.0,
+            tick_time: Duration::from_millis(0),
+            draws: 0,
+            frames: 0,
+            frame_time: Duration::from_millis(0),
+            ticks: 0,
+            tick_total: Duration::from_millis(0)
+        }
+    }
+    /// Records a rendered frame, averaging once a second has accumulated
+    /// takes   time since the previous frame as Duration
+    ///         draw calls issued by the frame as u32
+    pub fn record_frame(&mut self, elapsed: Duration, draws: u32) {
+        self.draws = draws;
+        self.frames += 1;
+        self.frame_time += elapsed;
+        if self.frame_time >= Duration::from_secs(1) {
+            self.fps = self.frames as f32 / self.frame_time.as_secs_f32();
+            self.tick_time = if self.ticks > 0 {
+                self.tick_total / self.ticks
+            } else {
+                Duration::from_millis(0)
+            };
+            self.frames = 0;
+            self.frame_time = Duration::from_millis(0);
+            self.ticks = 0;
+            self.tick_total = Duration::from_millis(0);
+        }
+    }
+    /// Records the time spent simulating a single fixed update
+    /// takes   time spent as Duration
+    pub fn record_tick(&mut self, elapsed: Duration) {
+        self.ticks += 1;
+        self.tick_total += elapsed;
+    }
+    pub fn get_fps(&self) -> f32 {
+        self.fps
+    }
+    pub fn get_tick_time(&self) -> Duration {
+        self.tick_time
+    }
+    pub fn get_draws(&self) -> u32 {
+        self.draws
+    }
+}
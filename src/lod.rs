@@ -0,0 +1,126 @@
+use obj::TexturedVertex;
+
+/// Simplifies a mesh by repeatedly collapsing its shortest edge
+/// takes   vertices as [TexturedVertex]
+///         triangle indices as [u16]
+///         fraction of triangles to keep as f32
+/// returns simplified vertices and indices as (Vec<TexturedVertex>, Vec<u16>)
+pub fn simplify(vertices: &[TexturedVertex], indices: &[u16], ratio: f32) -> (Vec<TexturedVertex>, Vec<u16>) {
+    // vertices split along uv seams share a position, collapse them together so seams stay closed
+    let mut groups: Vec<usize> = Vec::with_capacity(vertices.len());
+    let mut group_positions: Vec<[f32; 3]> = Vec::new();
+    for vertex in vertices {
+        match group_positions.iter().position(|position| *position == vertex.position) {
+            Some(group) => groups.push(group),
+            None => {
+                groups.push(group_positions.len());
+                group_positions.push(vertex.position);
+            }
+        }
+    }
+    let mut triangles: Vec<[usize; 3]> = indices.chunks(3)
+        .map(|face| [face[0] as usize, face[1] as usize, face[2] as usize])
+        .collect();
+    let target = ((triangles.len() as f32 * ratio.clamp(0.0, 1.0)).ceil() as usize).max(1);
+    let mut merged: Vec<usize> = (0..group_positions.len()).collect();
+    let find = |merged: &Vec<usize>, mut group: usize| {
+        while merged[group] != group {
+            group = merged[group];
+        }
+        group
+    };
+    while triangles.len() > target {
+        // shortest edge between two distinct position groups
+        let mut shortest: Option<(usize, usize, f32)> = None;
+        for triangle in &triangles {
+            for corner in 0..3 {
+                let a = find(&merged, groups[triangle[corner]]);
+                let b = find(&merged, groups[triangle[(corner + 1) % 3]]);
+                if a == b {
+                    continue;
+                }
+                let length = distance_squared(group_positions[a], group_positions[b]);
+                if shortest.is_none_or(|(_, _, shortest_length)| length < shortest_length) {
+                    shortest = Some((a, b, length));
+                }
+            }
+        }
+        let (keep, remove) = match shortest {
+            Some((a, b, _)) => (a, b),
+            None => break
+        };
+        let pa = group_positions[keep];
+        let pb = group_positions[remove];
+        group_positions[keep] = [(pa[0] + pb[0]) / 2.0, (pa[1] + pb[1]) / 2.0, (pa[2] + pb[2]) / 2.0];
+        merged[remove] = keep;
+        // drop triangles that lost an edge to the collapse
+        triangles.retain(|triangle| {
+            let a = find(&merged, groups[triangle[0]]);
+            let b = find(&merged, groups[triangle[1]]);
+            let c = find(&merged, groups[triangle[2]]);
+            a != b && b != c && a != c
+        });
+    }
+    // compact the surviving vertices, moving each to its group's collapsed position
+    let mut remap: Vec<Option<u16>> = vec![None; vertices.len()];
+    let mut simplified_vertices = Vec::new();
+    let mut simplified_indices = Vec::with_capacity(triangles.len() * 3);
+    for triangle in &triangles {
+        for &index in triangle {
+            let new_index = match remap[index] {
+                Some(new_index) => new_index,
+                None => {
+                    let mut vertex = vertices[index];
+                    vertex.position = group_positions[find(&merged, groups[index])];
+                    simplified_vertices.push(vertex);
+                    let new_index = (simplified_vertices.len() - 1) as u16;
+                    remap[index] = Some(new_index);
+                    new_index
+                }
+            };
+            simplified_indices.push(new_index);
+        }
+    }
+    (simplified_vertices, simplified_indices)
+}
+
+fn distance_squared(a: [f32; 3], b: [f32; 3]) -> f32 {
+    let d = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    d[0] * d[0] + d[1] * d[1] + d[2] * d[2]
+}
+
+/// Estimates the fraction of the screen height covered by a bounding sphere
+/// takes   sphere center as [f32; 3]
+///         sphere radius as f32
+///         view transformation as [[f32; 4]; 4]
+///         perspective transformation as [[f32; 4]; 4]
+/// returns projected diameter over screen height as f32
+pub fn screen_size(center: [f32; 3], radius: f32, view: [[f32; 4]; 4], perspective: [[f32; 4]; 4]) -> f32 {
+    // view space depth of the center, matrices are column major
+    let depth = view[0][2] * center[0] + view[1][2] * center[1] + view[2][2] * center[2] + view[3][2];
+    // perspective projections divide by depth, orthographic ones leave w at one
+    let w = perspective[2][3] * depth + perspective[3][3];
+    if perspective[2][3] != 0.0 && depth <= radius {
+        return f32::INFINITY;
+    }
+    radius * perspective[1][1] / w
+}
+
+/// Chooses a level of detail, only switching once the size is clear of the threshold
+/// takes   currently drawn level as usize
+///         screen size of the object as f32
+///         screen size below which each coarser level is used as [f32]
+///         fraction of a threshold to wait past before switching as f32
+/// returns level to draw as usize
+pub fn select_lod(current: usize, size: f32, thresholds: &[f32], hysteresis: f32) -> usize {
+    let mut level = current.min(thresholds.len());
+    // coarser while below the next threshold
+    while level < thresholds.len() && size < thresholds[level] * (1.0 - hysteresis) {
+        level += 1;
+    }
+    // finer while above the threshold that led to this level
+    while level > 0 && size > thresholds[level - 1] * (1.0 + hysteresis) {
+        level -= 1;
+    }
+    level
+}
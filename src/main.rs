@@ -1,12 +1,30 @@
 extern crate glium;
 
 use glium::{glutin, Surface};
-use navmesh::{NavMesh,NavVec3};
 use rand::Rng;
 
+mod agent;
+mod atlas;
+mod avoidance;
+mod compressed;
+mod debug;
+mod entity;
 mod etc;
-mod body;
 mod gfx;
+mod hud;
+mod lod;
+mod navigation;
+mod obstacle;
+mod path;
+mod physics;
+mod pick;
+mod scene;
+mod shape;
+mod simulation;
+mod steering;
+mod text;
+mod transform;
+mod world;
 
 const MS_PER_UPDATE: u32 = 16;
 
@@ -14,11 +32,49 @@ fn main() {
     #[allow(unused_imports)]
     use crate::{
         etc::*,
-        body::Body,
+        debug::DebugRenderer,
         gfx::{
+            DrawContext,
             Graphic,
-            GraphicLibrary
-        }
+            GraphicLibrary,
+            RenderQueue
+        },
+        hud::{
+            FrameStats,
+            Hud,
+            HudAnchor,
+            HudPanel
+        },
+        navigation::NavMeshLibrary,
+        obstacle::{
+            CarvedNavMesh,
+            Obstacle,
+            ObstacleShape
+        },
+        physics::{
+            PhysicsSettings,
+            RollResult
+        },
+        pick::{
+            pick_entities,
+            Ray
+        },
+        scene::{
+            Scene,
+            SceneCamera,
+            SceneLight
+        },
+        shape::ShapeLibrary,
+        simulation::{
+            RollRecord,
+            Simulation
+        },
+        text::{
+            BitmapFont,
+            TextRenderer
+        },
+        transform::Transform,
+        world::World
     };
 
     let event_loop = glutin::event_loop::EventLoop::new();
@@ -29,6 +85,9 @@ fn main() {
     let load_time = std::time::Instant::now();
     let library = GraphicLibrary::load(&display, "assets");
     println!("Loaded assets folder in {:?}", load_time.elapsed());
+    for (page, utilisation) in library.get_atlas_utilisation().iter().enumerate() {
+        println!("Atlas page {} is {:.1}% used", page, utilisation * 100.0);
+    }
 
     let scale = [0.25, 0.25, 0.25];
     let orientation = [0.0, 0.0, 0.0, 1.0];
@@ -40,118 +99,86 @@ fn main() {
             z: cgmath::Deg(0.0)
         }).into();
     */
-    // construct navmesh verticies
-    let vertices = vec![
-        (1.0,1.0,0.0).into(),
-        (-1.0,1.0,0.0).into(),
-        (1.0,-1.0,0.0).into(),
-        (-1.0,-1.0,0.0).into()
-    ];
-    // construct navmesh triangles
-    let triangles = vec![
-        (0,1,2).into(),
-        (1,2,3).into()
-    ];
-    // construct navmesh object
-    let nav_mesh = NavMesh::new(vertices,triangles).unwrap();
-    let test1 = NavVec3 {
-        x: 1.0,
-        y: 2.0,
-        z: 3.0,
-    };
-    let test2 = NavVec3 {
-        x: 1.0,
-        y: 1.0,
-        z: 1.0
+    // bake the table's navmesh, or read it from the cache when the table hasn't changed
+    let nav_meshes = NavMeshLibrary::load("assets");
+    // obstacles are cut out wider by the agent radius the table was baked for
+    let mut nav_mesh = CarvedNavMesh::new(nav_meshes.get_nav_mesh("table"), 0.1);
+    // the dice cup and the box the dice came in stand on the table for walkers to path around
+    nav_mesh.add_obstacle(Obstacle::new([0.6, -0.6, 0.0], ObstacleShape::Cylinder { radius: 0.15 }));
+    nav_mesh.add_obstacle(Obstacle::new([-0.6, 0.6, 0.0], ObstacleShape::Box { half_extents: [0.2, 0.1], angle: 0.5 }));
+
+    // --seed n picks the roll, --replay file plays back a roll saved with R
+    let args: Vec<String> = std::env::args().collect();
+    fn usage(error: &str) -> ! {
+        eprintln!("{}", error);
+        eprintln!("Usage: tstp [--seed n | --replay file]");
+        std::process::exit(2)
+    }
+    let simulation = match (args.get(1).map(String::as_str), args.get(2)) {
+        (Some("--seed"), Some(seed)) => Simulation::new(seed.parse().unwrap_or_else(|why| usage(&format!("Bad seed {}: {}", seed, why)))),
+        (Some("--replay"), Some(json_file_path)) => Simulation::replay(RollRecord::load(json_file_path).unwrap_or_else(|why| usage(&why))),
+        (Some(option @ ("--seed" | "--replay")), None) => usage(&format!("{} needs a value", option)),
+        _ => Simulation::new(std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64)
     };
-    let test3 = test1 - test2;
-    println!("{:?}", test3);
-
-    // construct bodies to move and draw
-    let origin = [0.0, 0.0, 0.0];
-    let mut bodies = [
-        Body::new(
-            1.0,
-            [0.0, 0.0, 0.0],
-            origin,
-            orientation,
-            Graphic::new(
-                scale,
-                "tetrahedron".to_string(),
-                "d4texture".to_string())),
-        Body::new(
-            1.0,
-            [1.0, 1.0, 0.0],
-            origin,
-            orientation, 
-            Graphic::new(
-                scale,
-                "hexahedron".to_string(),
-                "d6texture".to_string())),
-        Body::new(
-            1.0,
-            [0.0, 1.0, 0.0],
-            origin,
-            orientation, 
-            Graphic::new(
-                scale,
-                "octahedron".to_string(),
-                "d8texture".to_string())),
-        Body::new(
-            1.0,
-            [0.0, 1.0, 0.0],
-            origin,
-            orientation, 
-            Graphic::new(
-                scale,
-                "trapezohedron".to_string(),
-                "d10texture".to_string())),
-        Body::new(
-            1.0,
-            [0.0, -1.0, 0.0],
-            origin,
-            orientation, 
-            Graphic::new(
-                scale,
-                "dodecahedron".to_string(),
-                "d12texture".to_string())),
-        Body::new(
-            1.0,
-            [-1.0, 0.0, 0.0],
-            origin,
-            orientation, 
-            Graphic::new(
-                scale,
-                "icosahedron".to_string(),
-                "d20texture".to_string()))
-    ];
+    let mut world = World::new(nav_mesh, PhysicsSettings::table([2.0, 2.0]), simulation);
 
-    // give waypoints to bodies
-    let mut rng = rand::thread_rng();
-    for body in &mut bodies {
-        let rn1 = rng.gen::<f32>() * 2.0 - 1.0;
-        let rn2 = rng.gen::<f32>() * 2.0 - 1.0;
-        println!("({},{},0.0)", rn1, rn2);
-        body.set_waypoint(
-            &nav_mesh,
-            (
-                rn1,
-                rn2,
-                0.0
-            ).into()
-        );
+    // add cameras to the world's scene around the table
+    let scene = world.get_scene_mut();
+    let camera_node = scene.add_node(None, Transform::look_at([0.5, 0.2, -3.0], [0.0, 0.0, 0.0], [0.0, 1.0, 0.0]));
+    scene.attach_camera(camera_node, SceneCamera::new(std::f32::consts::FRAC_PI_3, 0.1, 1024.0));
+    let top_camera_node = scene.add_node(None, Transform::look_at([0.0, 0.0, -3.0], [0.0, 0.0, 0.0], [0.0, 1.0, 0.0]));
+    scene.attach_camera(top_camera_node, SceneCamera::orthographic(4.5, 0.1, 16.0));
+    let mut active_camera = camera_node;
+    // the light is an entity like the dice, the render system gives it a node
+    let entities = world.get_entities_mut();
+    let light = entities.spawn(Transform::look_at([0.0, 0.0, 0.0], [1.4, 0.4, 0.7], [0.0, 1.0, 0.0]));
+    entities.insert_light(light, SceneLight::new([1.0, 1.0, 1.0]));
+
+    // make dice in a ring above the table and throw them onto it
+    let shapes = ShapeLibrary::load("assets");
+    // meshes sit unrotated at their entity's origin
+    let mesh = Transform::new([0.0, 0.0, 0.0], [1.0, 0.0, 0.0, 0.0], scale);
+    let dice = [
+        ("tetrahedron", "d4texture", [0.5, 0.0, -0.5]),
+        ("hexahedron", "d6texture", [0.25, 0.433, -0.5]),
+        ("octahedron", "d8texture", [-0.25, 0.433, -0.5]),
+        ("trapezohedron", "d10texture", [-0.5, 0.0, -0.5]),
+        ("dodecahedron", "d12texture", [-0.25, -0.433, -0.5]),
+        ("icosahedron", "d20texture", [0.25, -0.433, -0.5])
+    ];
+    for (object_key, texture_key, position) in dice.iter() {
+        let die = world.add_die(shapes.get_shape(object_key), *position, orientation,
+            Graphic::new(mesh, object_key.to_string(), texture_key.to_string()));
+        world.random_throw(die);
     }
 
     let program = build_program(&display, "assets/vertex_shader.glsl", "assets/fragment_shader.glfl");
+    let mut text_renderer = TextRenderer::new(&display, BitmapFont::builtin(&display),
+        "assets/text_vertex_shader.glsl", "assets/text_fragment_shader.glfl");
+    let mut debug_renderer = DebugRenderer::new(&display, "assets/debug_vertex_shader.glsl", "assets/debug_fragment_shader.glfl");
+    let mut hud = Hud::new(2.0, 6.0);
+    let stats_panel = hud.add_panel(HudPanel::new(HudAnchor::TopLeft, [8.0, 8.0], [0.0, 0.0, 0.0, 0.5]));
+    let rolls_panel = hud.add_panel(HudPanel::new(HudAnchor::TopRight, [8.0, 8.0], [0.0, 0.0, 0.0, 0.5]));
+    let mut render_queue = RenderQueue::new();
+    let mut stats = FrameStats::new();
+    let mut cursor = [0.0f32, 0.0];
+    let mut camera = ([[0.0f32; 4]; 4], [[0.0f32; 4]; 4], [1.0f32, 1.0]);
+    let mut selected = None;
+    let mut last_frame_time = std::time::Instant::now();
     let mut last_time = std::time::Instant::now();
-    let mut lag = 0u32;
+    // kept at full precision so sub millisecond remainders add up across events
+    let mut lag = std::time::Duration::from_millis(0);
+    let tick = std::time::Duration::from_millis(MS_PER_UPDATE as u64);
     event_loop.run(move |event, _, control_flow| {
         let next_frame_time = std::time::Instant::now() +
             std::time::Duration::from_nanos(16_666_667);
         *control_flow = glutin::event_loop::ControlFlow::WaitUntil(next_frame_time);
 // update clock
         let current_time = std::time::Instant::now();
-        let elapsed_time = current_time.duration_since(last_time).as_millis() as u32;
+        let elapsed_time = current_time.duration_since(last_time);
         last_time = current_time;
         lag += elapsed_time;
 
@@ -162,6 +189,60 @@ fn main() {
                     *control_flow = glutin::event_loop::ControlFlow::Exit;
                     return;
                 },
+                glutin::event::WindowEvent::CursorMoved { position, .. } => {
+                    cursor = [position.x as f32, position.y as f32];
+                    return;
+                },
+                glutin::event::WindowEvent::MouseInput {
+                    state: glutin::event::ElementState::Pressed,
+                    button,
+                    ..
+                } => {
+                    match button {
+                        // select the die under the cursor
+                        glutin::event::MouseButton::Left => {
+                            let (view, perspective, dimensions) = camera;
+                            selected = Ray::from_cursor(cursor, dimensions, view, perspective)
+                                .and_then(|ray| pick_entities(&ray, world.get_entities(), world.get_scene(), &library));
+                        },
+                        // re-roll the selected die
+                        glutin::event::MouseButton::Right => if let Some(hit) = selected {
+                            // flick the die up from where it was clicked
+                            let rn1 = world.get_simulation_mut().get_rng_mut().gen::<f32>() * 2.0 - 1.0;
+                            let rn2 = world.get_simulation_mut().get_rng_mut().gen::<f32>() * 2.0 - 1.0;
+                            world.throw(hit.entity, [rn1, rn2, -3.0], hit.point);
+                        },
+                        _ => ()
+                    }
+                    return;
+                },
+                // toggle debug render modes
+                glutin::event::WindowEvent::KeyboardInput {
+                    input: glutin::event::KeyboardInput {
+                        state: glutin::event::ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                    ..
+                } => {
+                    let mode = debug_renderer.get_mode_mut();
+                    match key {
+                        glutin::event::VirtualKeyCode::Key1 => mode.wireframe = !mode.wireframe,
+                        glutin::event::VirtualKeyCode::Key2 => mode.normals = !mode.normals,
+                        glutin::event::VirtualKeyCode::Key3 => mode.uv_checker = !mode.uv_checker,
+                        glutin::event::VirtualKeyCode::Key4 => mode.flat_normals = !mode.flat_normals,
+                        // switch between the angled and top down cameras
+                        glutin::event::VirtualKeyCode::Key5 => active_camera = if active_camera == camera_node {
+                            top_camera_node
+                        } else {
+                            camera_node
+                        },
+                        // save the roll so far for replaying with --replay
+                        glutin::event::VirtualKeyCode::R => world.get_simulation().get_record().save("roll.json"),
+                        _ => ()
+                    }
+                    return;
+                },
                 _ => return,
             },
             glutin::event::Event::NewEvents(cause) => match cause {
@@ -172,21 +253,27 @@ fn main() {
             _ =>  (),
         }
 // update
-        while lag >= MS_PER_UPDATE {
-            for body in &mut bodies {
-                body.update_time_step(&nav_mesh, MS_PER_UPDATE as f32 / 1000.0);
-            };
-            lag -= MS_PER_UPDATE;
+        while lag >= tick {
+            let tick_time = std::time::Instant::now();
+            world.step(MS_PER_UPDATE as f32 / 1000.0);
+            stats.record_tick(tick_time.elapsed());
+            lag -= tick;
         }
 // render
         let mut target = display.draw();
         target.clear_color_and_depth((0.0, 0.0, 1.0, 1.0), 1.0);
         
         
-        let view = view_matrix(&[0.5, 0.2, -3.0], &[-0.5, -0.2, 3.0], &[0.0, 1.0, 0.0]);
-        let perspective = perspective_matrix(&target);
+        // draw between the last two ticks by the share of a tick left over
+        let alpha = lag.as_secs_f32() / tick.as_secs_f32();
+        world.sync(alpha);
+        let scene = world.get_scene_mut();
+        let (width, height) = target.get_dimensions();
+        let view = scene.get_view(active_camera);
+        let perspective = scene.get_node(active_camera).get_camera().unwrap()
+            .get_projection(width as f32, height as f32);
 
-        let light = [1.4, 0.4, 0.7f32];
+        let light = scene.get_light_direction().unwrap();
 
         let params = glium::DrawParameters {
             depth: glium::Depth {
@@ -197,10 +284,47 @@ fn main() {
             .. Default::default()
         };
 
-        for body in &mut bodies {
-            body.draw(&mut target, &library, view, perspective, light, &program, &params);
+        render_queue.set_camera(view, perspective);
+        scene.queue(&mut render_queue, &library);
+        let context = DrawContext {
+            view,
+            perspective,
+            light,
+            program: &program,
+            params: &params,
+            debug: &debug_renderer
         };
+        let mut draws = library.draw_queue(&mut target, &mut render_queue, &context);
+
+        let white = [1.0, 1.0, 1.0, 1.0];
+        let yellow = [1.0, 1.0, 0.0, 1.0];
+        let panel = hud.get_panel_mut(stats_panel);
+        panel.set_label(0, format!("FPS: {:.1}", stats.get_fps()), white);
+        panel.set_label(1, format!("TICK: {:.3} MS", stats.get_tick_time().as_secs_f32() * 1000.0), white);
+        panel.set_label(2, format!("DRAWS: {}", stats.get_draws()), white);
+        panel.set_label(3, format!("MODE: {}", debug_renderer.get_mode().get_name()), white);
+        let simulation = world.get_simulation();
+        panel.set_label(4, format!("{}: {}", if simulation.is_replaying() { "REPLAY" } else { "SEED" }, simulation.get_seed()), white);
+        let panel = hud.get_panel_mut(rolls_panel);
+        for (line, (entity, roll)) in world.get_rolls().into_iter().enumerate() {
+            let key = world.get_entities().get_graphic(entity).map_or("", |graphic| graphic.get_object_key());
+            let roll = match roll {
+                RollResult::Face(value) => value.to_string(),
+                RollResult::Rolling => "ROLLING".to_string(),
+                RollResult::Cocked => "COCKED".to_string()
+            };
+            match selected {
+                Some(hit) if hit.entity == entity => panel.set_label(line,
+                    format!("{}: {} FACE {}", key, roll, hit.face), yellow),
+                _ => panel.set_label(line, format!("{}: {}", key, roll), white)
+            }
+        }
+        camera = (view, perspective, [width as f32, height as f32]);
+        hud.queue(&mut text_renderer, [width as f32, height as f32]);
+        draws += text_renderer.draw(&display, &mut target);
 
         target.finish().unwrap();
+        stats.record_frame(last_frame_time.elapsed(), draws);
+        last_frame_time = std::time::Instant::now();
     });
 }
\ No newline at end of file
@@ -0,0 +1,391 @@
+use cgmath::{
+    InnerSpace,
+    Vector3,
+    Zero
+};
+use navmesh::{
+    NavMesh,
+    NavVec3
+};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path
+};
+
+use crate::{
+    etc::load_bytes,
+    pick::Ray,
+    shape::Shape
+};
+
+// world up of the table, against gravity
+const DEFAULT_UP: [f32; 3] = [0.0, 0.0, -1.0];
+// clearance rays start this far above the floor so they miss the triangle they leave
+const CLEARANCE_OFFSET: f32 = 1.0e-4;
+// sharpest corner whose offset is mitred, sharper corners are moved less than the radius
+const MIN_MITRE: f32 = 0.25;
+// changing the bake changes this so older caches are baked again
+const BAKE_VERSION: u32 = 1;
+
+/// Size and climbing ability of the agents a navigation mesh is baked for
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NavBakeSettings {
+    agent_radius: f32,
+    agent_height: f32,
+    max_slope: f32,
+    up: [f32; 3]
+}
+#[allow(dead_code)]
+impl NavBakeSettings {
+    /// Constructs bake settings
+    /// takes   agent radius as f32
+    ///         agent height as f32
+    ///         steepest walkable slope in radians as f32
+    ///         world up as [f32; 3]
+    /// returns bake settings as NavBakeSettings
+    pub fn new(agent_radius: f32, agent_height: f32, max_slope: f32, up: [f32; 3]) -> NavBakeSettings {
+        NavBakeSettings {
+            agent_radius,
+            agent_height,
+            max_slope,
+            up
+        }
+    }
+    /// Reads bake settings from a manifest, the slope is given in degrees and up defaults to -z
+    /// takes   manifest bake object as serde_json::Value
+    /// returns bake settings as NavBakeSettings
+    pub fn from_json(value: &serde_json::Value) -> NavBakeSettings {
+        let up = value["up"].as_array().map_or(DEFAULT_UP, |up| [
+            up[0].as_f64().unwrap() as f32,
+            up[1].as_f64().unwrap() as f32,
+            up[2].as_f64().unwrap() as f32
+        ]);
+        NavBakeSettings::new(
+            value["agent_radius"].as_f64().unwrap() as f32,
+            value["agent_height"].as_f64().unwrap() as f32,
+            (value["max_slope"].as_f64().unwrap() as f32).to_radians(),
+            up
+        )
+    }
+    pub fn get_agent_radius(&self) -> f32 {
+        self.agent_radius
+    }
+    pub fn get_agent_height(&self) -> f32 {
+        self.agent_height
+    }
+    pub fn get_max_slope(&self) -> f32 {
+        self.max_slope
+    }
+    pub fn get_up(&self) -> [f32; 3] {
+        self.up
+    }
+}
+
+/// Constructs a navigation mesh from indexed triangles
+/// takes   positions as [[f32; 3]]
+///         triangles as [[usize; 3]]
+/// returns navigation mesh as NavMesh
+pub fn build_nav_mesh(positions: &[[f32; 3]], triangles: &[[usize; 3]]) -> NavMesh {
+    let vertices = positions.iter()
+        .map(|position| NavVec3::new(position[0], position[1], position[2]))
+        .collect();
+    let triangles = triangles.iter()
+        .map(|triangle| (triangle[0] as u32, triangle[1] as u32, triangle[2] as u32).into())
+        .collect();
+    NavMesh::new(vertices, triangles).unwrap()
+}
+
+/// Reads a navigation mesh from an obj file holding only walkable triangles, or a json file
+/// with vertices and triangles arrays
+/// takes   obj or json file path as str
+/// returns navigation mesh as NavMesh
+pub fn load_nav_mesh(file_path: &str) -> NavMesh {
+    match Path::new(file_path).extension().and_then(std::ffi::OsStr::to_str) {
+        Some("obj") => {
+            let shape = Shape::from_path(file_path);
+            build_nav_mesh(shape.get_positions(), shape.get_triangles())
+        },
+        Some("json") => {
+            let (positions, triangles) = read_json(&serde_json::from_slice(&load_bytes(file_path)).unwrap());
+            build_nav_mesh(&positions, &triangles)
+        },
+        _ => panic!("Could not recognize the navigation mesh format of '{}'", file_path)
+    }
+}
+
+/// Bakes level geometry into a navigation mesh, reading the cache instead when it was baked from
+/// the same level file with the same settings and writing it otherwise
+/// takes   level obj file path as str
+///         bake settings as NavBakeSettings
+///         cache file path as str
+/// returns navigation mesh as NavMesh
+pub fn bake_cached(level_file_path: &str, settings: &NavBakeSettings, cache_file_path: &str) -> NavMesh {
+    let source_hash = bake_hash(&load_bytes(level_file_path), settings);
+    // a missing, unreadable or stale cache is baked again
+    let cached = fs::read(cache_file_path).ok()
+        .and_then(|bytes| serde_json::from_slice::<serde_json::Value>(&bytes).ok())
+        .filter(|buffer| buffer["source_hash"].as_str() == Some(source_hash.as_str()));
+    if let Some(buffer) = cached {
+        let (positions, triangles) = read_json(&buffer);
+        return build_nav_mesh(&positions, &triangles);
+    }
+    let shape = Shape::from_path(level_file_path);
+    let (positions, triangles) = bake(shape.get_positions(), shape.get_triangles(), settings);
+    let buffer = json!({
+        "source_hash": source_hash,
+        "vertices": positions,
+        "triangles": triangles
+    });
+    // the bake is still good without a cache, it is just baked again next time
+    if let Err(why) = fs::write(cache_file_path, serde_json::to_vec(&buffer).unwrap()) {
+        eprintln!("Couldn't write {}: {}", cache_file_path, why);
+    }
+    build_nav_mesh(&positions, &triangles)
+}
+
+/// Derives the surface agents can walk on from level geometry, keeping triangles no steeper than
+/// the max slope with the agent height clear above them and moving open edges in by the agent
+/// radius so agent centres stay that far from drops and walls
+///
+/// The edges are offset by moving their vertices, so floors should be made of triangles larger
+/// than the radius, smaller ones along an edge are turned over by the offset and dropped
+/// takes   positions as [[f32; 3]]
+///         triangles wound so their normals point up as [[usize; 3]]
+///         bake settings as NavBakeSettings
+/// returns walkable positions and triangles as (Vec<[f32; 3]>, Vec<[usize; 3]>)
+pub fn bake(positions: &[[f32; 3]], triangles: &[[usize; 3]], settings: &NavBakeSettings) -> (Vec<[f32; 3]>, Vec<[usize; 3]>) {
+    let up = Vector3::from(settings.up).normalize();
+    let points: Vec<Vector3<f32>> = positions.iter().map(|&position| Vector3::from(position)).collect();
+    let corners = |triangle: &[usize; 3], points: &[Vector3<f32>]| (points[triangle[0]], points[triangle[1]], points[triangle[2]]);
+    let min_up = settings.max_slope.cos();
+    let walkable: Vec<[usize; 3]> = triangles.iter()
+        .enumerate()
+        .filter(|(index, triangle)| {
+            let (a, b, c) = corners(triangle, &points);
+            let normal = (b - a).cross(c - a);
+            let length = normal.magnitude();
+            if length <= f32::EPSILON || normal.dot(up) < min_up * length {
+                return false;
+            }
+            let ray = Ray::new(((a + b + c) / 3.0 + up * CLEARANCE_OFFSET).into(), up.into());
+            !triangles.iter()
+                .enumerate()
+                .filter(|(other_index, _)| other_index != index)
+                .filter_map(|(_, other)| {
+                    let (a, b, c) = corners(other, &points);
+                    ray.intersect_triangle(a, b, c)
+                })
+                .any(|distance| distance < settings.agent_height)
+        })
+        .map(|(_, triangle)| *triangle)
+        .collect();
+    // open edges belong to a single walkable triangle
+    let mut edge_count: HashMap<(usize, usize), usize> = HashMap::new();
+    for triangle in &walkable {
+        for corner in 0..3 {
+            let (a, b) = (triangle[corner], triangle[(corner + 1) % 3]);
+            *edge_count.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+        }
+    }
+    let mut inwards: Vec<Vec<Vector3<f32>>> = vec![Vec::new(); points.len()];
+    for triangle in &walkable {
+        for corner in 0..3 {
+            let (a, b, c) = (triangle[corner], triangle[(corner + 1) % 3], triangle[(corner + 2) % 3]);
+            if edge_count[&(a.min(b), a.max(b))] != 1 {
+                continue;
+            }
+            let mut inward = up.cross(points[b] - points[a]).normalize();
+            if inward.dot(points[c] - points[a]) < 0.0 {
+                inward = -inward;
+            }
+            inwards[a].push(inward);
+            inwards[b].push(inward);
+        }
+    }
+    let eroded: Vec<Vector3<f32>> = points.iter()
+        .zip(&inwards)
+        .map(|(point, inwards)| {
+            let direction = inwards.iter().fold(Vector3::zero(), |sum, inward| sum + inward);
+            if direction.magnitude2() <= f32::EPSILON {
+                // interior vertices stay, as do vertices between opposite edges of a sliver
+                return *point;
+            }
+            let direction = direction.normalize();
+            // mitred so every edge at a corner moves the full radius
+            let mitre = inwards.iter().map(|inward| inward.dot(direction)).fold(1.0, f32::min).max(MIN_MITRE);
+            point + direction * (settings.agent_radius / mitre)
+        })
+        .collect();
+    // triangles the offset turned over or collapsed are narrower than the agent
+    let kept: Vec<[usize; 3]> = walkable.into_iter()
+        .filter(|triangle| {
+            let (a, b, c) = corners(triangle, &eroded);
+            (b - a).cross(c - a).dot(up) > f32::EPSILON
+        })
+        .collect();
+    // only the vertices of kept triangles are written
+    let mut remap = vec![None; points.len()];
+    let mut baked_positions = Vec::new();
+    let baked_triangles = kept.iter()
+        .map(|triangle| {
+            let mut baked = [0; 3];
+            for corner in 0..3 {
+                baked[corner] = *remap[triangle[corner]].get_or_insert_with(|| {
+                    baked_positions.push(eroded[triangle[corner]].into());
+                    baked_positions.len() - 1
+                });
+            }
+            baked
+        })
+        .collect();
+    (baked_positions, baked_triangles)
+}
+
+// reads the vertices and triangles arrays shared by navigation mesh files and caches
+fn read_json(buffer: &serde_json::Value) -> (Vec<[f32; 3]>, Vec<[usize; 3]>) {
+    let positions = buffer["vertices"].as_array().unwrap().iter()
+        .map(|vertex| [
+            vertex[0].as_f64().unwrap() as f32,
+            vertex[1].as_f64().unwrap() as f32,
+            vertex[2].as_f64().unwrap() as f32
+        ])
+        .collect();
+    let triangles = buffer["triangles"].as_array().unwrap().iter()
+        .map(|triangle| [
+            triangle[0].as_u64().unwrap() as usize,
+            triangle[1].as_u64().unwrap() as usize,
+            triangle[2].as_u64().unwrap() as usize
+        ])
+        .collect();
+    (positions, triangles)
+}
+
+// identifies a bake by its source, settings and bake version
+fn bake_hash(source: &[u8], settings: &NavBakeSettings) -> String {
+    let mut bytes = BAKE_VERSION.to_le_bytes().to_vec();
+    bytes.extend_from_slice(source);
+    for value in [settings.agent_radius, settings.agent_height, settings.max_slope].iter().chain(settings.up.iter()) {
+        bytes.extend_from_slice(&value.to_bits().to_le_bytes());
+    }
+    format!("{:016x}", fnv1a(&bytes))
+}
+
+// 64 bit fnv-1a, the same on every build unlike the standard library's hashers
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+/// Navigation meshes by key, read from manifests holding a nav_mesh_key along with either a
+/// nav_mesh_file_path or a level_file_path to bake with the manifest's bake settings
+#[derive(Debug, Clone)]
+pub struct NavMeshLibrary {
+    nav_mesh_dict: HashMap<String, NavMesh>,
+    cache_directory: Option<String>
+}
+#[allow(dead_code)]
+impl NavMeshLibrary {
+    pub fn new() -> NavMeshLibrary {
+        NavMeshLibrary {
+            nav_mesh_dict: HashMap::new(),
+            cache_directory: None
+        }
+    }
+    /// Keeps bake caches in a folder of its own instead of where the manifests put them,
+    /// each cache keeps the file name its manifest gives it
+    /// takes   folder path as str
+    pub fn set_cache_directory(&mut self, directory: &str) {
+        self.cache_directory = Some(directory.to_string());
+    }
+    /// Loads the navigation meshes of every manifest in a folder, skipping manifests of other assets
+    /// takes   folder path as str
+    /// returns navigation mesh library as NavMeshLibrary
+    pub fn load(file_path: &str) -> NavMeshLibrary {
+        let mut library = NavMeshLibrary::new();
+        library.load_path(file_path);
+        library
+    }
+    pub fn load_path(&mut self, file_path: &str) {
+        let paths = fs::read_dir(file_path).unwrap();
+        for path in paths {
+            let file = path.unwrap().path();
+            let extension = file.extension().and_then(std::ffi::OsStr::to_str).unwrap();
+            match extension {
+                "json" => self.load_json(file.to_str().unwrap()),
+                _=> continue
+            }
+        }
+    }
+    pub fn load_json(&mut self, json_file_path: &str) {
+        let buffer: serde_json::Value = serde_json::from_slice(&load_bytes(json_file_path)).unwrap();
+        let nav_mesh_key = match buffer["nav_mesh_key"].as_str() {
+            Some(nav_mesh_key) => nav_mesh_key.to_string(),
+            None => return
+        };
+        let nav_mesh = match (buffer["nav_mesh_file_path"].as_str(), buffer["level_file_path"].as_str()) {
+            (Some(nav_mesh_file_path), _) => load_nav_mesh(nav_mesh_file_path),
+            (None, Some(level_file_path)) => {
+                let settings = NavBakeSettings::from_json(&buffer["bake"]);
+                match buffer["cache_file_path"].as_str() {
+                    Some(cache_file_path) => {
+                        let cache_file_path = match self.cache_directory.as_ref() {
+                            Some(directory) => Path::new(directory).join(Path::new(cache_file_path).file_name().unwrap()),
+                            None => Path::new(cache_file_path).to_path_buf()
+                        };
+                        bake_cached(level_file_path, &settings, cache_file_path.to_str().unwrap())
+                    },
+                    None => {
+                        let shape = Shape::from_path(level_file_path);
+                        let (positions, triangles) = bake(shape.get_positions(), shape.get_triangles(), &settings);
+                        build_nav_mesh(&positions, &triangles)
+                    }
+                }
+            },
+            (None, None) => panic!("Navigation mesh '{}' has neither a nav_mesh_file_path nor a level_file_path", nav_mesh_key)
+        };
+        self.nav_mesh_dict.insert(nav_mesh_key, nav_mesh);
+    }
+    pub fn get_nav_mesh(&self, nav_mesh_key: &str) -> &NavMesh {
+        match self.nav_mesh_dict.get(nav_mesh_key) {
+            Some(nav_mesh) => nav_mesh,
+            None => panic!("Could not find navigation mesh key '{}' in library", nav_mesh_key)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv1a_matches_reference_values() {
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn bake_cached_returns_the_bake_when_the_cache_cannot_be_written() {
+        let settings = NavBakeSettings::new(0.1, 0.5, 40f32.to_radians(), DEFAULT_UP);
+        let nav_mesh = bake_cached("assets/table.obj", &settings, "assets/missing/table.navcache");
+        assert!(!nav_mesh.triangles().is_empty());
+    }
+
+    #[test]
+    fn libraries_keep_their_caches_in_the_cache_directory() {
+        let directory = std::env::temp_dir().join(format!("tstp_nav_cache_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let mut library = NavMeshLibrary::new();
+        library.set_cache_directory(directory.to_str().unwrap());
+        library.load_path("assets");
+        let cache = fs::read(directory.join("table.navcache"));
+        // loading again reads the cache just written
+        let mut cached = NavMeshLibrary::new();
+        cached.set_cache_directory(directory.to_str().unwrap());
+        cached.load_path("assets");
+        fs::remove_dir_all(&directory).unwrap();
+        assert!(cache.is_ok());
+        assert_eq!(library.get_nav_mesh("table").triangles().len(), cached.get_nav_mesh("table").triangles().len());
+    }
+}
@@ -0,0 +1,351 @@
+use cgmath::{
+    InnerSpace,
+    Vector2,
+    Vector3
+};
+use navmesh::NavMesh;
+
+use crate::navigation::build_nav_mesh;
+
+// sides of the polygon standing in for a cylinder, drawn around the circle so it covers it
+const CYLINDER_SEGMENTS: usize = 12;
+// carved vertices closer than this are the same vertex
+const WELD_DISTANCE: f32 = 1.0e-4;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ObstacleShape {
+    Box {
+        half_extents: [f32; 2],
+        angle: f32
+    },
+    Cylinder {
+        radius: f32
+    }
+}
+
+/// Obstacle standing upright on the table, blocking the navigation mesh under its footprint
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Obstacle {
+    position: [f32; 3],
+    shape: ObstacleShape
+}
+#[allow(dead_code)]
+impl Obstacle {
+    pub fn new(position: [f32; 3], shape: ObstacleShape) -> Obstacle {
+        Obstacle {
+            position,
+            shape
+        }
+    }
+    pub fn get_position(&self) -> [f32; 3] {
+        self.position
+    }
+    pub fn get_shape(&self) -> ObstacleShape {
+        self.shape
+    }
+    /// Outlines the obstacle on the table
+    /// takes   margin to grow the outline by as f32
+    /// returns outline as Footprint
+    pub fn footprint(&self, margin: f32) -> Footprint {
+        let centre = Vector2::new(self.position[0], self.position[1]);
+        let points = match self.shape {
+            ObstacleShape::Box { half_extents, angle } => {
+                let (sin, cos) = angle.sin_cos();
+                let x = Vector2::new(cos, sin) * (half_extents[0] + margin);
+                let y = Vector2::new(-sin, cos) * (half_extents[1] + margin);
+                vec![centre - x - y, centre + x - y, centre + x + y, centre - x + y]
+            },
+            ObstacleShape::Cylinder { radius } => {
+                let step = std::f32::consts::PI * 2.0 / CYLINDER_SEGMENTS as f32;
+                let radius = (radius + margin) / (step / 2.0).cos();
+                (0..CYLINDER_SEGMENTS)
+                    .map(|segment| {
+                        let (sin, cos) = (step * segment as f32).sin_cos();
+                        centre + Vector2::new(cos, sin) * radius
+                    })
+                    .collect()
+            }
+        };
+        Footprint {
+            points
+        }
+    }
+}
+
+/// Convex outline in the xy plane of the table, wound counter-clockwise
+#[derive(Debug, Clone, PartialEq)]
+pub struct Footprint {
+    points: Vec<Vector2<f32>>
+}
+#[allow(dead_code)]
+impl Footprint {
+    pub fn get_points(&self) -> Vec<[f32; 2]> {
+        self.points.iter().map(|point| (*point).into()).collect()
+    }
+    /// Tests whether a segment passes through the outline, ignoring z
+    /// takes   segment ends as Vector3<f32>
+    /// returns whether any of the segment is inside as bool
+    pub fn crosses(&self, start: Vector3<f32>, end: Vector3<f32>) -> bool {
+        let start = start.truncate();
+        let direction = end.truncate() - start;
+        let mut enter: f32 = 0.0;
+        let mut exit: f32 = 1.0;
+        for (point, outward) in self.edges() {
+            let distance = outward.dot(start - point);
+            let approach = outward.dot(direction);
+            if approach.abs() <= f32::EPSILON {
+                if distance > 0.0 {
+                    return false;
+                }
+                continue;
+            }
+            let t = -distance / approach;
+            if approach < 0.0 {
+                enter = enter.max(t);
+            } else {
+                exit = exit.min(t);
+            }
+            if enter > exit {
+                return false;
+            }
+        }
+        true
+    }
+    // each edge as its start and outward normal
+    fn edges(&self) -> impl Iterator<Item = (Vector2<f32>, Vector2<f32>)> + '_ {
+        self.points.iter()
+            .zip(self.points.iter().cycle().skip(1))
+            .map(|(start, end)| (*start, Vector2::new(end.y - start.y, start.x - end.x)))
+    }
+    // splits a convex polygon into the convex pieces outside the outline
+    fn subtract(&self, polygon: Vec<Vector3<f32>>) -> Vec<Vec<Vector3<f32>>> {
+        let mut pieces = Vec::new();
+        let mut remaining = polygon;
+        for (point, outward) in self.edges() {
+            let outside = clip(&remaining, point, -outward);
+            if outside.len() >= 3 {
+                pieces.push(outside);
+            }
+            remaining = clip(&remaining, point, outward);
+            if remaining.len() < 3 {
+                // the rest of the polygon was outside too
+                return pieces;
+            }
+        }
+        pieces
+    }
+}
+
+// keeps the part of a polygon behind a line, on the side opposite the normal
+fn clip(polygon: &[Vector3<f32>], point: Vector2<f32>, normal: Vector2<f32>) -> Vec<Vector3<f32>> {
+    let side = |vertex: &Vector3<f32>| normal.dot(vertex.truncate() - point);
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for (start, end) in polygon.iter().zip(polygon.iter().cycle().skip(1)) {
+        let (start_side, end_side) = (side(start), side(end));
+        if start_side <= 0.0 {
+            clipped.push(*start);
+        }
+        if (start_side < 0.0 && end_side > 0.0) || (start_side > 0.0 && end_side < 0.0) {
+            clipped.push(start + (end - start) * (start_side / (start_side - end_side)));
+        }
+    }
+    clipped
+}
+
+/// Cuts footprints out of a navigation mesh, splitting the triangles they overlap into the pieces
+/// left around them
+/// takes   positions as [[f32; 3]]
+///         triangles as [[usize; 3]]
+///         footprints as [Footprint]
+/// returns carved positions and triangles as (Vec<[f32; 3]>, Vec<[usize; 3]>)
+pub fn carve(positions: &[[f32; 3]], triangles: &[[usize; 3]], footprints: &[Footprint]) -> (Vec<[f32; 3]>, Vec<[usize; 3]>) {
+    let mut polygons: Vec<Vec<Vector3<f32>>> = triangles.iter()
+        .map(|triangle| triangle.iter().map(|&index| Vector3::from(positions[index])).collect())
+        .collect();
+    for footprint in footprints {
+        polygons = polygons.into_iter().flat_map(|polygon| footprint.subtract(polygon)).collect();
+    }
+    // pieces share vertices by position so the mesh stays connected across cuts
+    let mut vertices: Vec<Vector3<f32>> = Vec::new();
+    let rings: Vec<Vec<usize>> = polygons.iter()
+        .filter(|polygon| area(polygon) > WELD_DISTANCE * WELD_DISTANCE)
+        .map(|polygon| polygon.iter()
+            .map(|point| match vertices.iter().position(|vertex| (vertex - point).magnitude() <= WELD_DISTANCE) {
+                Some(index) => index,
+                None => {
+                    vertices.push(*point);
+                    vertices.len() - 1
+                }
+            })
+            .collect())
+        .collect();
+    let welded = vertices.len();
+    let mut carved_triangles = Vec::new();
+    for ring in rings {
+        // a cut along one side of an edge leaves vertices on it that the other side needs too
+        let mut split = Vec::new();
+        for (&start, &end) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+            if start == end {
+                continue;
+            }
+            split.push(start);
+            let edge = vertices[end] - vertices[start];
+            let mut between: Vec<(f32, usize)> = (0..welded)
+                .filter(|&index| index != start && index != end)
+                .filter_map(|index| {
+                    let t = edge.dot(vertices[index] - vertices[start]) / edge.magnitude2();
+                    let distance = (vertices[start] + edge * t - vertices[index]).magnitude();
+                    if t > 0.0 && t < 1.0 && distance <= WELD_DISTANCE { Some((t, index)) } else { None }
+                })
+                .collect();
+            between.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+            split.extend(between.into_iter().map(|(_, index)| index));
+        }
+        if split.len() < 3 {
+            continue;
+        }
+        if split.len() == 3 {
+            carved_triangles.push([split[0], split[1], split[2]]);
+            continue;
+        }
+        // fanning from the centre keeps vertices along a side from making flat triangles
+        let centre = split.iter().fold(Vector3::new(0.0, 0.0, 0.0), |sum, &index| sum + vertices[index]) / split.len() as f32;
+        vertices.push(centre);
+        let centre = vertices.len() - 1;
+        for (&start, &end) in split.iter().zip(split.iter().cycle().skip(1)) {
+            carved_triangles.push([centre, start, end]);
+        }
+    }
+    (vertices.into_iter().map(Vector3::into).collect(), carved_triangles)
+}
+
+// area of a planar convex polygon
+fn area(polygon: &[Vector3<f32>]) -> f32 {
+    polygon.iter()
+        .skip(1)
+        .zip(polygon.iter().skip(2))
+        .fold(Vector3::new(0.0, 0.0, 0.0), |sum, (b, c)| sum + (b - polygon[0]).cross(c - polygon[0]))
+        .magnitude() / 2.0
+}
+
+pub type ObstacleId = usize;
+
+/// Navigation mesh with obstacles cut out of it, carved again whenever obstacles change
+#[derive(Debug, Clone)]
+pub struct CarvedNavMesh {
+    positions: Vec<[f32; 3]>,
+    triangles: Vec<[usize; 3]>,
+    margin: f32,
+    obstacles: Vec<Option<Obstacle>>,
+    nav_mesh: NavMesh,
+    changed: bool,
+    blocked: Vec<Footprint>
+}
+#[allow(dead_code)]
+impl CarvedNavMesh {
+    /// Constructs a carved navigation mesh without obstacles
+    /// takes   navigation mesh to carve as NavMesh
+    ///         margin to grow footprints by, the agent radius the mesh was baked for, as f32
+    /// returns carved navigation mesh as CarvedNavMesh
+    pub fn new(nav_mesh: &NavMesh, margin: f32) -> CarvedNavMesh {
+        CarvedNavMesh {
+            positions: nav_mesh.vertices().iter().map(|vertex| [vertex.x, vertex.y, vertex.z]).collect(),
+            triangles: nav_mesh.triangles().iter()
+                .map(|triangle| [triangle.first as usize, triangle.second as usize, triangle.third as usize])
+                .collect(),
+            margin,
+            obstacles: Vec::new(),
+            nav_mesh: nav_mesh.clone(),
+            changed: false,
+            blocked: Vec::new()
+        }
+    }
+    pub fn add_obstacle(&mut self, obstacle: Obstacle) -> ObstacleId {
+        self.blocked.push(obstacle.footprint(self.margin));
+        self.obstacles.push(Some(obstacle));
+        self.changed = true;
+        self.obstacles.len() - 1
+    }
+    pub fn move_obstacle(&mut self, obstacle_id: ObstacleId, position: [f32; 3]) {
+        match self.obstacles.get_mut(obstacle_id) {
+            Some(Some(obstacle)) => {
+                obstacle.position = position;
+                self.blocked.push(obstacle.footprint(self.margin));
+                self.changed = true;
+            },
+            _ => panic!("Could not find obstacle {} to move", obstacle_id)
+        }
+    }
+    pub fn remove_obstacle(&mut self, obstacle_id: ObstacleId) {
+        match self.obstacles.get_mut(obstacle_id) {
+            Some(obstacle) if obstacle.is_some() => {
+                *obstacle = None;
+                self.changed = true;
+            },
+            _ => panic!("Could not find obstacle {} to remove", obstacle_id)
+        }
+    }
+    pub fn get_obstacle(&self, obstacle_id: ObstacleId) -> Option<&Obstacle> {
+        self.obstacles.get(obstacle_id)?.as_ref()
+    }
+    pub fn get_nav_mesh(&self) -> &NavMesh {
+        &self.nav_mesh
+    }
+    /// Carves the mesh again if obstacles were added, moved or removed, once however many changed
+    /// returns footprints newly blocked since the last update as Vec<Footprint>
+    pub fn update(&mut self) -> Vec<Footprint> {
+        if self.changed {
+            let footprints: Vec<Footprint> = self.obstacles.iter()
+                .flatten()
+                .map(|obstacle| obstacle.footprint(self.margin))
+                .collect();
+            let (positions, triangles) = carve(&self.positions, &self.triangles, &footprints);
+            self.nav_mesh = build_nav_mesh(&positions, &triangles);
+            self.changed = false;
+        }
+        self.blocked.drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use navmesh::{
+        NavPathMode,
+        NavQuery,
+        NavVec3
+    };
+
+    #[test]
+    fn carving_a_square_leaves_a_connected_ring_around_it() {
+        let positions = [[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [1.0, 1.0, 0.0], [-1.0, 1.0, 0.0]];
+        let obstacle = Obstacle::new([0.0, 0.0, 0.0], ObstacleShape::Box { half_extents: [0.25, 0.25], angle: 0.0 });
+        let (positions, triangles) = carve(&positions, &[[0, 1, 2], [0, 2, 3]], &[obstacle.footprint(0.0)]);
+        let corners = |triangle: &[usize; 3]| -> Vec<Vector3<f32>> { triangle.iter().map(|&index| Vector3::from(positions[index])).collect() };
+        // the hole's area is gone and nothing is left inside it
+        let total: f32 = triangles.iter().map(|triangle| area(&corners(triangle))).sum();
+        assert!((total - (4.0 - 0.25)).abs() < 1e-4, "carved area was {}", total);
+        for triangle in triangles.iter() {
+            let centre = corners(triangle).iter().fold(Vector3::new(0.0, 0.0, 0.0), |sum, corner| sum + corner) / 3.0;
+            assert!(centre.x.abs() >= 0.25 || centre.y.abs() >= 0.25, "triangle {:?} is inside the hole", triangle);
+        }
+        // every triangle reaches every other across shared edges
+        let mut reached = vec![false; triangles.len()];
+        let mut open = vec![0];
+        reached[0] = true;
+        while let Some(current) = open.pop() {
+            for (other, triangle) in triangles.iter().enumerate() {
+                let shared = triangle.iter().filter(|index| triangles[current].contains(index)).count();
+                if !reached[other] && shared == 2 {
+                    reached[other] = true;
+                    open.push(other);
+                }
+            }
+        }
+        assert!(reached.iter().all(|reached| *reached));
+        // the navigation mesh built from it still joins the two sides of the hole
+        let nav_mesh = build_nav_mesh(&positions, &triangles);
+        let path = nav_mesh.find_path(NavVec3::new(-0.8, 0.0, 0.0), NavVec3::new(0.8, 0.0, 0.0), NavQuery::Accuracy, NavPathMode::MidPoints);
+        assert!(path.is_some_and(|path| !path.is_empty()));
+    }
+}
@@ -0,0 +1,401 @@
+use cgmath::{
+    InnerSpace,
+    Vector3
+};
+use navmesh::{
+    NavMesh,
+    NavQuery,
+    NavVec3
+};
+use std::collections::HashMap;
+
+// nothing on the table walks a curve yet
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PathSmoothing {
+    None,
+    CatmullRom,
+    Bezier
+}
+
+/// How paths found on the navigation mesh are straightened and smoothed before agents follow them,
+/// curves are sampled at the sample spacing
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PathSettings {
+    string_pull: bool,
+    corner_offset: f32,
+    smoothing: PathSmoothing,
+    sample_spacing: f32
+}
+#[allow(dead_code)]
+impl PathSettings {
+    /// Constructs path settings
+    /// takes   whether to pull the path tight through the mesh as bool
+    ///         distance to keep from the mesh's edges at corners, usually the agent radius, as f32
+    ///         smoothing as PathSmoothing
+    ///         distance between curve samples as f32
+    /// returns path settings as PathSettings
+    pub fn new(string_pull: bool, corner_offset: f32, smoothing: PathSmoothing, sample_spacing: f32) -> PathSettings {
+        PathSettings {
+            string_pull,
+            corner_offset,
+            smoothing,
+            sample_spacing
+        }
+    }
+    /// Settings following the edge midpoints the navigation mesh returns as they are
+    /// returns path settings as PathSettings
+    pub fn midpoints() -> PathSettings {
+        PathSettings::new(false, 0.0, PathSmoothing::None, 0.05)
+    }
+    /// Settings pulling paths tight round corners without smoothing them
+    /// takes   distance to keep from the mesh's edges at corners as f32
+    /// returns path settings as PathSettings
+    pub fn funnel(corner_offset: f32) -> PathSettings {
+        PathSettings::new(true, corner_offset, PathSmoothing::None, 0.05)
+    }
+    pub fn get_string_pull(&self) -> bool {
+        self.string_pull
+    }
+    pub fn get_corner_offset(&self) -> f32 {
+        self.corner_offset
+    }
+    pub fn get_smoothing(&self) -> PathSmoothing {
+        self.smoothing
+    }
+    pub fn get_sample_spacing(&self) -> f32 {
+        self.sample_spacing
+    }
+    /// Straightens a path found on the mesh into its corners
+    /// takes   navigation mesh the path was found on as NavMesh
+    ///         path as [NavVec3]
+    /// returns corners as Vec<[f32; 3]>
+    pub fn corners(&self, nav_mesh: &NavMesh, path: &[NavVec3]) -> Vec<[f32; 3]> {
+        let points: Vec<Vector3<f32>> = path.iter().map(|point| Vector3::new(point.x, point.y, point.z)).collect();
+        let corners = if self.string_pull { string_pull(nav_mesh, &points, self.corner_offset) } else { points };
+        corners.into_iter().map(Vector3::into).collect()
+    }
+    /// Smooths corners into a curve
+    /// takes   corners as [[f32; 3]]
+    /// returns curve when smoothing as Option<ArcPath>
+    pub fn curve(&self, corners: &[[f32; 3]]) -> Option<ArcPath> {
+        let corners: Vec<Vector3<f32>> = corners.iter().map(|&corner| Vector3::from(corner)).collect();
+        match self.smoothing {
+            PathSmoothing::None => None,
+            PathSmoothing::CatmullRom => Some(ArcPath::new(catmull_rom(&corners, self.sample_spacing))),
+            PathSmoothing::Bezier => Some(ArcPath::new(bezier(&corners, self.sample_spacing)))
+        }
+    }
+}
+
+/// Pulls a path tight, the shortest path through the triangles between its ends in the xy plane
+/// of the table, keeping corners the offset away from the mesh's outer edges
+/// takes   navigation mesh the path was found on as NavMesh
+///         path as [Vector3<f32>]
+///         corner offset as f32
+/// returns corners from start to end as Vec<Vector3<f32>>
+pub fn string_pull(nav_mesh: &NavMesh, path: &[Vector3<f32>], corner_offset: f32) -> Vec<Vector3<f32>> {
+    if path.len() < 2 {
+        return path.to_vec();
+    }
+    let (start, end) = (path[0], path[path.len() - 1]);
+    // the mesh's midpoint paths leave out edges a straight line already crosses, so the funnel
+    // goes through every edge of the triangles the path crosses instead
+    let nav_point = |point: Vector3<f32>| NavVec3::new(point.x, point.y, point.z);
+    let triangles = match (
+        nav_mesh.find_closest_triangle(nav_point(start), NavQuery::Accuracy),
+        nav_mesh.find_closest_triangle(nav_point(end), NavQuery::Accuracy)
+    ) {
+        (Some(first), Some(last)) => match nav_mesh.find_path_triangles(first, last) {
+            Some((triangles, _)) => triangles,
+            None => return path.to_vec()
+        },
+        _ => return path.to_vec()
+    };
+    let vertices: Vec<Vector3<f32>> = nav_mesh.vertices().iter().map(|vertex| Vector3::new(vertex.x, vertex.y, vertex.z)).collect();
+    let corners_of = |triangle: usize| {
+        let triangle = nav_mesh.triangles()[triangle];
+        [triangle.first as usize, triangle.second as usize, triangle.third as usize]
+    };
+    let mut edge_count: HashMap<(usize, usize), usize> = HashMap::new();
+    for triangle in 0..nav_mesh.triangles().len() {
+        let corners = corners_of(triangle);
+        for corner in 0..3 {
+            let (a, b) = (corners[corner], corners[(corner + 1) % 3]);
+            *edge_count.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+        }
+    }
+    let mut outer = vec![false; vertices.len()];
+    for (&(a, b), &count) in &edge_count {
+        if count == 1 {
+            outer[a] = true;
+            outer[b] = true;
+        }
+    }
+    let centre = |corners: [usize; 3]| (vertices[corners[0]] + vertices[corners[1]] + vertices[corners[2]]) / 3.0;
+    // each pair of neighbouring triangles shares an edge the path has to cross, the portal
+    let mut portals = vec![(start, start)];
+    for pair in triangles.windows(2) {
+        let (from, to) = (corners_of(pair[0]), corners_of(pair[1]));
+        let shared: Vec<usize> = from.iter().copied().filter(|corner| to.contains(corner)).collect();
+        let (a, b) = (shared[0], shared[1]);
+        let point = (vertices[a] + vertices[b]) / 2.0;
+        let heading = centre(to) - centre(from);
+        let (left, right) = if cross(heading, vertices[a] - point) > 0.0 { (a, b) } else { (b, a) };
+        let (left_point, right_point) = (vertices[left], vertices[right]);
+        let width = (right_point - left_point).magnitude();
+        let inset = corner_offset.min(width / 2.0);
+        let across = (right_point - left_point) / width.max(f32::EPSILON);
+        portals.push((
+            if outer[left] { left_point + across * inset } else { left_point },
+            if outer[right] { right_point - across * inset } else { right_point }
+        ));
+    }
+    portals.push((end, end));
+    // simple stupid funnel, narrowing the funnel portal by portal and turning a corner where it closes
+    let mut corners = vec![path[0]];
+    let (mut apex, mut left, mut right) = (path[0], path[0], path[0]);
+    let (mut left_index, mut right_index) = (0, 0);
+    let mut index = 1;
+    while index < portals.len() {
+        let (portal_left, portal_right) = portals[index];
+        if cross(right - apex, portal_right - apex) >= 0.0 {
+            if apex == right || cross(left - apex, portal_right - apex) < 0.0 {
+                right = portal_right;
+                right_index = index;
+            } else {
+                apex = left;
+                push_corner(&mut corners, apex);
+                right = apex;
+                right_index = left_index;
+                index = left_index + 1;
+                continue;
+            }
+        }
+        if cross(left - apex, portal_left - apex) <= 0.0 {
+            if apex == left || cross(right - apex, portal_left - apex) > 0.0 {
+                left = portal_left;
+                left_index = index;
+            } else {
+                apex = right;
+                push_corner(&mut corners, apex);
+                left = apex;
+                left_index = right_index;
+                index = right_index + 1;
+                continue;
+            }
+        }
+        index += 1;
+    }
+    push_corner(&mut corners, end);
+    corners
+}
+
+// portals sharing a mesh vertex can turn the funnel at the same corner twice
+fn push_corner(corners: &mut Vec<Vector3<f32>>, corner: Vector3<f32>) {
+    if corners.last() != Some(&corner) {
+        corners.push(corner);
+    }
+}
+
+// z of the cross product, positive when b is anticlockwise of a in the xy plane
+fn cross(a: Vector3<f32>, b: Vector3<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Samples a Catmull-Rom spline through every corner, bulging past corners on sharp turns
+/// takes   corners as [Vector3<f32>]
+///         distance between samples as f32
+/// returns samples from the first corner to the last as Vec<Vector3<f32>>
+pub fn catmull_rom(corners: &[Vector3<f32>], spacing: f32) -> Vec<Vector3<f32>> {
+    if corners.len() < 3 {
+        return corners.to_vec();
+    }
+    let mut samples = vec![corners[0]];
+    for index in 0..corners.len() - 1 {
+        // the ends are repeated to give the first and last segments their missing neighbours
+        let p0 = corners[index.saturating_sub(1)];
+        let (p1, p2) = (corners[index], corners[index + 1]);
+        let p3 = corners[(index + 2).min(corners.len() - 1)];
+        let count = ((p2 - p1).magnitude() / spacing).ceil().max(1.0) as usize;
+        for sample in 1..=count {
+            let t = sample as f32 / count as f32;
+            let (t2, t3) = (t * t, t * t * t);
+            samples.push((p1 * 2.0 + (p2 - p0) * t + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+                + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3) * 0.5);
+        }
+    }
+    samples
+}
+
+/// Samples quadratic Bezier curves rounding each corner between the midpoints of its sides,
+/// the curve cuts inside the corners rather than passing through them
+/// takes   corners as [Vector3<f32>]
+///         distance between samples as f32
+/// returns samples from the first corner to the last as Vec<Vector3<f32>>
+pub fn bezier(corners: &[Vector3<f32>], spacing: f32) -> Vec<Vector3<f32>> {
+    if corners.len() < 3 {
+        return corners.to_vec();
+    }
+    let mut samples = vec![corners[0]];
+    for index in 1..corners.len() - 1 {
+        let start = (corners[index - 1] + corners[index]) / 2.0;
+        let control = corners[index];
+        let end = (corners[index] + corners[index + 1]) / 2.0;
+        let count = (((control - start).magnitude() + (end - control).magnitude()) / spacing).ceil().max(1.0) as usize;
+        // straight sides need no samples between the curves, which meet where a side is shared
+        push_corner(&mut samples, start);
+        for sample in 1..=count {
+            let t = sample as f32 / count as f32;
+            samples.push(start * ((1.0 - t) * (1.0 - t)) + control * (2.0 * t * (1.0 - t)) + end * (t * t));
+        }
+    }
+    samples.push(corners[corners.len() - 1]);
+    samples
+}
+
+/// Polyline measured along its length so it can be followed by distance travelled
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArcPath {
+    points: Vec<Vector3<f32>>,
+    distances: Vec<f32>
+}
+#[allow(dead_code)]
+impl ArcPath {
+    pub fn new(points: Vec<Vector3<f32>>) -> ArcPath {
+        let mut distances = Vec::with_capacity(points.len());
+        let mut distance = 0.0;
+        for (index, point) in points.iter().enumerate() {
+            if index > 0 {
+                distance += (point - points[index - 1]).magnitude();
+            }
+            distances.push(distance);
+        }
+        ArcPath {
+            points,
+            distances
+        }
+    }
+    pub fn get_points(&self) -> Vec<[f32; 3]> {
+        self.points.iter().map(|point| (*point).into()).collect()
+    }
+    pub fn get_length(&self) -> f32 {
+        self.distances.last().copied().unwrap_or(0.0)
+    }
+    /// Finds the point a distance along the path
+    /// takes   distance from the start, clamped to the path, as f32
+    /// returns point as Vector3<f32>
+    pub fn point_at(&self, distance: f32) -> Vector3<f32> {
+        let distance = distance.clamp(0.0, self.get_length());
+        let index = self.distances.iter().position(|&end| end >= distance).unwrap_or(0).max(1).min(self.points.len() - 1);
+        let (start, end) = (self.distances[index - 1], self.distances[index]);
+        let t = if end > start { (distance - start) / (end - start) } else { 0.0 };
+        self.points[index - 1] + (self.points[index] - self.points[index - 1]) * t
+    }
+    /// Finds how far along the path the point nearest a position is, only looking between two
+    /// distances so a path passing near itself does not skip ahead
+    /// takes   position as Vector3<f32>
+    ///         distances to look between as f32
+    /// returns distance from the start as f32
+    pub fn project(&self, position: Vector3<f32>, from: f32, to: f32) -> f32 {
+        let mut nearest = (f32::INFINITY, from);
+        for index in 1..self.points.len() {
+            let (start, end) = (self.distances[index - 1], self.distances[index]);
+            if end < from || start > to || end <= start {
+                continue;
+            }
+            let along = (self.points[index] - self.points[index - 1]).normalize().dot(position - self.points[index - 1]);
+            let distance = (start + along).clamp(from.max(start), to.min(end));
+            let offset = (self.point_at(distance) - position).magnitude();
+            if offset < nearest.0 {
+                nearest = (offset, distance);
+            }
+        }
+        nearest.1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navigation::build_nav_mesh;
+
+    fn assert_corners(corners: &[Vector3<f32>], expected: &[[f32; 2]]) {
+        assert_eq!(corners.len(), expected.len(), "corners were {:?}", corners);
+        for (corner, expected) in corners.iter().zip(expected.iter()) {
+            assert!((corner.x - expected[0]).abs() < 1e-5 && (corner.y - expected[1]).abs() < 1e-5, "corners were {:?}", corners);
+        }
+    }
+
+    #[test]
+    fn funnel_goes_straight_across_a_square() {
+        let nav_mesh = build_nav_mesh(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]], &[[0, 1, 2], [0, 2, 3]]);
+        let path = [Vector3::new(0.9, 0.2, 0.0), Vector3::new(0.5, 0.5, 0.0), Vector3::new(0.1, 0.6, 0.0)];
+        assert_corners(&string_pull(&nav_mesh, &path, 0.0), &[[0.9, 0.2], [0.1, 0.6]]);
+        assert_corners(&string_pull(&nav_mesh, &path, 0.1), &[[0.9, 0.2], [0.1, 0.6]]);
+    }
+
+    #[test]
+    fn funnel_turns_at_the_inner_corner_of_a_bend() {
+        // the shared edge runs from the origin to the inner corner at 0.6, 0.6
+        let nav_mesh = build_nav_mesh(&[[0.0, 0.0, 0.0], [2.0, 0.0, 0.0], [0.6, 0.6, 0.0], [0.0, 2.0, 0.0]], &[[0, 1, 2], [0, 2, 3]]);
+        let path = [Vector3::new(1.8, 0.1, 0.0), Vector3::new(0.3, 0.3, 0.0), Vector3::new(0.1, 1.8, 0.0)];
+        assert_corners(&string_pull(&nav_mesh, &path, 0.0), &[[1.8, 0.1], [0.6, 0.6], [0.1, 1.8]]);
+        // the offset moves the corner along the edge away from the outside of the bend
+        let inset = 0.6 - 0.1 / 2.0f32.sqrt();
+        assert_corners(&string_pull(&nav_mesh, &path, 0.1), &[[1.8, 0.1], [inset, inset], [0.1, 1.8]]);
+    }
+
+    // distance from a point to the nearest sample
+    fn distance_to(samples: &[Vector3<f32>], point: Vector3<f32>) -> f32 {
+        samples.iter().map(|sample| (sample - point).magnitude()).fold(f32::INFINITY, f32::min)
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_every_corner() {
+        let corners = [Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 0.0), Vector3::new(2.0, 1.0, 0.0)];
+        let samples = catmull_rom(&corners, 0.05);
+        for corner in corners.iter() {
+            assert!(distance_to(&samples, *corner) < 1e-5, "samples miss {:?}", corner);
+        }
+        assert!(samples.windows(2).all(|pair| (pair[1] - pair[0]).magnitude() < 0.1));
+    }
+
+    #[test]
+    fn bezier_rounds_corners_from_the_middle_of_their_sides() {
+        let corners = [Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 0.0)];
+        let samples = bezier(&corners, 0.05);
+        assert_eq!(samples[0], corners[0]);
+        assert_eq!(samples[samples.len() - 1], corners[2]);
+        assert!(distance_to(&samples, Vector3::new(0.5, 0.0, 0.0)) < 1e-5);
+        assert!(distance_to(&samples, Vector3::new(1.0, 0.5, 0.0)) < 1e-5);
+        // halfway round the curve is a quarter of the way from the corner to the middle of its sides
+        let nearest = distance_to(&samples, corners[1]);
+        assert!((nearest - 0.25 / 2.0f32.sqrt()).abs() < 0.01, "nearest sample {} from the corner", nearest);
+    }
+
+    #[test]
+    fn smoothing_settings_choose_the_curve() {
+        let corners = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]];
+        assert!(PathSettings::funnel(0.1).curve(&corners).is_none());
+        let through = PathSettings::new(true, 0.1, PathSmoothing::CatmullRom, 0.05).curve(&corners).unwrap();
+        let inside = PathSettings::new(true, 0.1, PathSmoothing::Bezier, 0.05).curve(&corners).unwrap();
+        assert!(distance_to(&through.points, Vector3::new(1.0, 0.0, 0.0)) < 1e-5);
+        assert!(distance_to(&inside.points, Vector3::new(1.0, 0.0, 0.0)) > 0.1);
+    }
+
+    #[test]
+    fn arc_paths_are_sampled_by_distance_along_them() {
+        // uneven segments, one long and two short
+        let path = ArcPath::new(vec![Vector3::new(0.0, 0.0, 0.0), Vector3::new(2.0, 0.0, 0.0), Vector3::new(2.0, 0.5, 0.0), Vector3::new(2.0, 1.0, 0.0)]);
+        assert!((path.get_length() - 3.0).abs() < 1e-5);
+        let samples: Vec<Vector3<f32>> = (0..=30).map(|step| path.point_at(step as f32 * 0.1)).collect();
+        for pair in samples.windows(2) {
+            assert!(((pair[1] - pair[0]).magnitude() - 0.1).abs() < 1e-4, "samples {:?} and {:?} are not 0.1 apart", pair[0], pair[1]);
+        }
+        assert!((path.point_at(2.5) - Vector3::new(2.0, 0.5, 0.0)).magnitude() < 1e-5);
+        assert_eq!(path.point_at(-1.0), Vector3::new(0.0, 0.0, 0.0));
+        assert_eq!(path.point_at(4.0), Vector3::new(2.0, 1.0, 0.0));
+        assert!((path.project(Vector3::new(1.0, 0.3, 0.0), 0.0, 3.0) - 1.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,602 @@
+use cgmath::{
+    InnerSpace,
+    Matrix,
+    Matrix3,
+    Quaternion,
+    SquareMatrix,
+    Vector3,
+    Zero
+};
+
+use crate::{
+    shape::{
+        FaceValue,
+        Shape
+    },
+    transform::Transform
+};
+
+// the table lies in the xy plane with the camera above it at -z
+const GRAVITY: [f32; 3] = [0.0, 0.0, 9.81];
+const CONTACT_ITERATIONS: usize = 4;
+// closing speed, on top of a tick of gravity, below which contacts stop bouncing,
+// keeps settled dice from jittering
+const RESTING_SPEED: f32 = 0.1;
+const PENETRATION_SLOP: f32 = 0.001;
+const PENETRATION_CORRECTION: f32 = 0.8;
+const CONTACT_DISTANCE: f32 = 0.01;
+// planes tilted less than this cosine from level hold bodies up, walls only stop them
+const SUPPORT_COSINE: f32 = 0.7;
+const SLEEP_SPEED: f32 = 0.15;
+const SLEEP_TIME: f32 = 0.5;
+const SLEEP_SMOOTHING: f32 = 0.2;
+// hull points this close to a plane count towards supporting a body at rest
+const SUPPORT_DISTANCE: f32 = 0.025;
+// hull normals and edges closer than this are merged into one separating axis
+const PARALLEL_DOT: f32 = 0.9999;
+// largest angle between a face direction and up still read as lying flat
+const COCKED_ANGLE: f32 = 8.0 * std::f32::consts::PI / 180.0;
+
+/// Minimum and maximum corners of a world space box
+pub type Bounds = ([f32; 3], [f32; 3]);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RollResult {
+    Rolling,
+    Face(u32),
+    Cocked
+}
+
+/// Half space boundary, points where normal . point + offset is negative are inside the boundary
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Plane {
+    normal: Vector3<f32>,
+    offset: f32
+}
+#[allow(dead_code)]
+impl Plane {
+    pub fn new(normal: [f32; 3], offset: f32) -> Plane {
+        let normal = Vector3::from(normal);
+        let length = normal.magnitude();
+        Plane {
+            normal: normal / length,
+            offset: offset / length
+        }
+    }
+    pub fn get_normal(&self) -> [f32; 3] {
+        self.normal.into()
+    }
+    pub fn distance(&self, point: [f32; 3]) -> f32 {
+        self.normal.dot(Vector3::from(point)) + self.offset
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PhysicsSettings {
+    gravity: Vector3<f32>,
+    planes: Vec<Plane>,
+    linear_damping: f32,
+    angular_damping: f32
+}
+#[allow(dead_code)]
+impl PhysicsSettings {
+    pub fn new(gravity: [f32; 3], planes: Vec<Plane>, linear_damping: f32, angular_damping: f32) -> PhysicsSettings {
+        PhysicsSettings {
+            gravity: gravity.into(),
+            planes,
+            linear_damping,
+            angular_damping
+        }
+    }
+    /// Constructs a walled table in the xy plane centred on the origin
+    /// takes   half the width and height of the table as [f32; 2]
+    /// returns settings with the table floor and four walls as PhysicsSettings
+    pub fn table(half_extents: [f32; 2]) -> PhysicsSettings {
+        PhysicsSettings::new(GRAVITY, vec![
+            Plane::new([0.0, 0.0, -1.0], 0.0),
+            Plane::new([1.0, 0.0, 0.0], half_extents[0]),
+            Plane::new([-1.0, 0.0, 0.0], half_extents[0]),
+            Plane::new([0.0, 1.0, 0.0], half_extents[1]),
+            Plane::new([0.0, -1.0, 0.0], half_extents[1])
+        ], 0.1, 0.3)
+    }
+    pub fn get_gravity(&self) -> [f32; 3] {
+        self.gravity.into()
+    }
+    pub fn get_planes(&self) -> &[Plane] {
+        &self.planes
+    }
+    fn supports(&self, plane: &Plane) -> bool {
+        -plane.normal.dot(self.gravity.normalize()) > SUPPORT_COSINE
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RigidBody {
+    mass: f32,
+    center: Vector3<f32>,
+    inverse_inertia: Matrix3<f32>,
+    hull: Vec<Vector3<f32>>,
+    faces: Vec<Plane>,
+    edges: Vec<Vector3<f32>>,
+    face_values: Vec<FaceValue>,
+    linear_velocity: Vector3<f32>,
+    angular_velocity: Vector3<f32>,
+    restitution: f32,
+    friction: f32,
+    motion: f32,
+    rest_time: f32,
+    resting: bool
+}
+
+/// Point where two bodies touch, the normal pointing from the first body to the second
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Contact {
+    point: Vector3<f32>,
+    normal: Vector3<f32>,
+    depth: f32
+}
+#[allow(dead_code)]
+impl RigidBody {
+    /// Constructs a rigid body with mass properties of a solid mesh
+    /// takes   shape as Shape
+    ///         placement of the mesh relative to the body, as it is drawn, as Transform
+    ///         mass as f32
+    ///         restitution as f32
+    ///         friction coefficient as f32
+    /// returns rigid body at rest as RigidBody
+    pub fn new(shape: &Shape, shape_transform: &Transform, mass: f32, restitution: f32, friction: f32) -> RigidBody {
+        let model = shape_transform.to_model_matrix();
+        let hull: Vec<Vector3<f32>> = shape.get_positions().iter()
+            .map(|position| (model * Vector3::from(*position).extend(1.0)).truncate())
+            .collect();
+        // a mirroring placement turns the triangles inside out
+        let triangles: Vec<[usize; 3]> = if model.determinant() < 0.0 {
+            shape.get_triangles().iter().map(|triangle| [triangle[0], triangle[2], triangle[1]]).collect()
+        } else {
+            shape.get_triangles().to_vec()
+        };
+        // the hull is the convex mesh itself, its face planes and edge directions are the separating axes
+        let mut faces: Vec<Plane> = Vec::new();
+        let mut edges: Vec<Vector3<f32>> = Vec::new();
+        for triangle in &triangles {
+            let (a, b, c) = (hull[triangle[0]], hull[triangle[1]], hull[triangle[2]]);
+            let normal = (b - a).cross(c - a);
+            if normal.magnitude2() > f32::EPSILON && !faces.iter().any(|face| face.normal.dot(normal.normalize()) > PARALLEL_DOT) {
+                let normal = normal.normalize();
+                faces.push(Plane { normal, offset: -normal.dot(a) });
+            }
+            for edge in [b - a, c - b, a - c].iter() {
+                if edge.magnitude2() > f32::EPSILON && !edges.iter().any(|other| other.dot(edge.normalize()).abs() > PARALLEL_DOT) {
+                    edges.push(edge.normalize());
+                }
+            }
+        }
+        let (volume, center, inertia) = mass_properties(&hull, &triangles);
+        let inertia = inertia * (mass / volume);
+        let face_values = shape.get_face_values().iter()
+            .map(|face| FaceValue {
+                direction: (model * Vector3::from(face.direction).extend(0.0)).truncate().normalize().into(),
+                value: face.value
+            })
+            .collect();
+        RigidBody {
+            mass,
+            center,
+            inverse_inertia: inertia.invert().unwrap_or_else(Matrix3::identity),
+            hull,
+            faces,
+            edges,
+            face_values,
+            linear_velocity: Vector3::zero(),
+            angular_velocity: Vector3::zero(),
+            restitution,
+            friction,
+            motion: SLEEP_SPEED,
+            rest_time: 0.0,
+            resting: false
+        }
+    }
+    pub fn get_mass(&self) -> f32 {
+        self.mass
+    }
+    pub fn get_center(&self) -> [f32; 3] {
+        self.center.into()
+    }
+    pub fn get_hull(&self) -> &[Vector3<f32>] {
+        &self.hull
+    }
+    /// Finds the radius of the sphere around the centre of mass holding the hull
+    /// returns radius as f32
+    pub fn get_radius(&self) -> f32 {
+        self.hull.iter().map(|point| (point - self.center).magnitude()).fold(0.0, f32::max)
+    }
+    pub fn get_linear_velocity(&self) -> [f32; 3] {
+        self.linear_velocity.into()
+    }
+    pub fn get_angular_velocity(&self) -> [f32; 3] {
+        self.angular_velocity.into()
+    }
+    pub fn is_resting(&self) -> bool {
+        self.resting
+    }
+    /// Finds the world space box around the hull, used to skip pairs of bodies that cannot touch
+    /// takes   body transform as Transform
+    /// returns minimum and maximum corners as Bounds
+    pub fn get_bounds(&self, transform: &Transform) -> Bounds {
+        let mut minimum = [f32::INFINITY; 3];
+        let mut maximum = [f32::NEG_INFINITY; 3];
+        for point in &self.hull {
+            let point = transform.transform_point((*point).into());
+            for axis in 0..3 {
+                minimum[axis] = minimum[axis].min(point[axis]);
+                maximum[axis] = maximum[axis].max(point[axis]);
+            }
+        }
+        (minimum, maximum)
+    }
+    /// Reads the die once it has settled
+    /// takes   body transform as Transform
+    ///         physics settings, up being against gravity, as PhysicsSettings
+    /// returns rolling, the face value pointing up or cocked, when the shape has face values, as Option<RollResult>
+    pub fn get_roll(&self, transform: &Transform, settings: &PhysicsSettings) -> Option<RollResult> {
+        if self.face_values.is_empty() {
+            return None;
+        }
+        if !self.resting {
+            return Some(RollResult::Rolling);
+        }
+        let up = -settings.gravity.normalize();
+        let (alignment, value) = self.face_values.iter()
+            .map(|face| (Vector3::from(transform.transform_vector(face.direction)).normalize().dot(up), face.value))
+            .fold((-1.0, 0), |best, face| if face.0 > best.0 { face } else { best });
+        if alignment < COCKED_ANGLE.cos() {
+            Some(RollResult::Cocked)
+        } else {
+            Some(RollResult::Face(value))
+        }
+    }
+    pub fn wake(&mut self) {
+        self.resting = false;
+        self.motion = SLEEP_SPEED;
+        self.rest_time = 0.0;
+    }
+    fn world_inverse_inertia(&self, transform: &Transform) -> Matrix3<f32> {
+        let rotation = Matrix3::from(transform.get_cgmath_orientation());
+        rotation * self.inverse_inertia * rotation.transpose()
+    }
+    fn world_center(&self, transform: &Transform) -> Vector3<f32> {
+        transform.transform_point(self.center.into()).into()
+    }
+    /// Pushes the body, waking it if it was resting
+    /// takes   body transform as Transform
+    ///         impulse as [f32; 3]
+    ///         world space point the impulse acts at as [f32; 3]
+    pub fn apply_impulse(&mut self, transform: &Transform, impulse: [f32; 3], point: [f32; 3]) {
+        let arm = Vector3::from(point) - self.world_center(transform);
+        let inverse_inertia = self.world_inverse_inertia(transform);
+        self.push(&inverse_inertia, Vector3::from(impulse), arm);
+        self.wake();
+    }
+    fn push(&mut self, inverse_inertia: &Matrix3<f32>, impulse: Vector3<f32>, arm: Vector3<f32>) {
+        self.push_scaled(1.0 / self.mass, inverse_inertia, impulse, arm);
+    }
+    fn push_scaled(&mut self, inverse_mass: f32, inverse_inertia: &Matrix3<f32>, impulse: Vector3<f32>, arm: Vector3<f32>) {
+        self.linear_velocity += impulse * inverse_mass;
+        self.angular_velocity += inverse_inertia * arm.cross(impulse);
+    }
+    fn world_hull(&self, transform: &Transform) -> Vec<Vector3<f32>> {
+        self.hull.iter()
+            .map(|point| Vector3::from(transform.transform_point((*point).into())))
+            .collect()
+    }
+    // bodies are unscaled, so planes move with the orientation and position alone
+    fn world_faces(&self, transform: &Transform) -> Vec<Plane> {
+        self.faces.iter()
+            .map(|face| {
+                let normal = Vector3::from(transform.transform_vector(face.normal.into()));
+                Plane { normal, offset: face.offset - normal.dot(transform.get_cgmath_position()) }
+            })
+            .collect()
+    }
+    /// Advances the body by a time step, bouncing it off the boundary planes
+    /// takes   body transform as Transform, moved in place
+    ///         physics settings as PhysicsSettings
+    ///         time step in seconds as f32
+    pub fn step(&mut self, transform: &mut Transform, settings: &PhysicsSettings, time_step: f32) {
+        if self.resting {
+            return;
+        }
+        self.linear_velocity += settings.gravity * time_step;
+        self.linear_velocity /= 1.0 + settings.linear_damping * time_step;
+        self.angular_velocity /= 1.0 + settings.angular_damping * time_step;
+        self.resolve_contacts(transform, settings, time_step);
+        // move the center of mass, then place the origin around it
+        let center = self.world_center(transform) + self.linear_velocity * time_step;
+        let orientation = transform.get_cgmath_orientation();
+        let spin = Quaternion::from_sv(0.0, self.angular_velocity) * orientation * (0.5 * time_step);
+        transform.orientation_to((orientation + spin).normalize().into());
+        let offset = Vector3::from(transform.transform_vector(self.center.into()));
+        transform.position_to((center - offset).into());
+        let contacts = self.correct_penetration(transform, settings);
+        // contact jitter is smoothed out of the speed so a settled body can still fall asleep
+        let speed = self.linear_velocity.magnitude().max(self.angular_velocity.magnitude());
+        self.motion += (speed - self.motion) * SLEEP_SMOOTHING;
+        // sleep once slow and supported for long enough, a body balanced on an edge or leaning on a wall must still tip over
+        if contacts >= 3 && self.motion < SLEEP_SPEED {
+            self.rest_time += time_step;
+            if self.rest_time >= SLEEP_TIME {
+                self.resting = true;
+                self.linear_velocity = Vector3::zero();
+                self.angular_velocity = Vector3::zero();
+            }
+        } else {
+            self.rest_time = 0.0;
+        }
+    }
+    fn resolve_contacts(&mut self, transform: &Transform, settings: &PhysicsSettings, time_step: f32) {
+        let resting_speed = RESTING_SPEED + settings.gravity.magnitude() * time_step * 2.0;
+        let center = self.world_center(transform);
+        let inverse_inertia = self.world_inverse_inertia(transform);
+        let points = self.world_hull(transform);
+        for _ in 0..CONTACT_ITERATIONS {
+            for plane in &settings.planes {
+                for point in &points {
+                    if plane.distance((*point).into()) > CONTACT_DISTANCE {
+                        continue;
+                    }
+                    let arm = point - center;
+                    let normal = plane.normal;
+                    let velocity = self.linear_velocity + self.angular_velocity.cross(arm);
+                    let closing = normal.dot(velocity);
+                    if closing >= 0.0 {
+                        continue;
+                    }
+                    let restitution = if -closing > resting_speed { self.restitution } else { 0.0 };
+                    let normal_mass = 1.0 / self.mass + normal.dot((inverse_inertia * arm.cross(normal)).cross(arm));
+                    let normal_impulse = -(1.0 + restitution) * closing / normal_mass;
+                    self.push(&inverse_inertia, normal * normal_impulse, arm);
+                    // coulomb friction opposes sliding, limited by the normal impulse
+                    let velocity = self.linear_velocity + self.angular_velocity.cross(arm);
+                    let sliding = velocity - normal * normal.dot(velocity);
+                    if sliding.magnitude2() < f32::EPSILON {
+                        continue;
+                    }
+                    let tangent = sliding.normalize();
+                    let tangent_mass = 1.0 / self.mass + tangent.dot((inverse_inertia * arm.cross(tangent)).cross(arm));
+                    let limit = self.friction * normal_impulse;
+                    let tangent_impulse = (-velocity.dot(tangent) / tangent_mass).max(-limit).min(limit);
+                    self.push(&inverse_inertia, tangent * tangent_impulse, arm);
+                }
+            }
+        }
+    }
+    fn correct_penetration(&self, transform: &mut Transform, settings: &PhysicsSettings) -> usize {
+        let mut contacts = 0;
+        for plane in &settings.planes {
+            let distances: Vec<f32> = self.hull.iter()
+                .map(|point| plane.distance(transform.transform_point((*point).into())))
+                .collect();
+            if settings.supports(plane) {
+                contacts += distances.iter().filter(|distance| **distance <= SUPPORT_DISTANCE).count();
+            }
+            let deepest = distances.iter().cloned().fold(f32::INFINITY, f32::min);
+            if -deepest > PENETRATION_SLOP {
+                let correction = plane.normal * ((-deepest - PENETRATION_SLOP) * PENETRATION_CORRECTION);
+                transform.position_by(correction.into());
+            }
+        }
+        contacts
+    }
+}
+
+/// Separates two bodies and exchanges impulses where they touch, a resting body is left asleep
+/// and treated as immovable unless the other hits it faster than it could have settled
+/// takes   first body and its transform as RigidBody and Transform, moved in place
+///         second body and its transform as RigidBody and Transform, moved in place
+///         physics settings as PhysicsSettings
+///         time step in seconds as f32
+pub fn collide(a: &mut RigidBody, a_transform: &mut Transform, b: &mut RigidBody, b_transform: &mut Transform,
+        settings: &PhysicsSettings, time_step: f32) {
+    if a.resting && b.resting {
+        return;
+    }
+    let contacts = find_contacts(a, a_transform, b, b_transform);
+    if contacts.is_empty() {
+        return;
+    }
+    let (a_center, b_center) = (a.world_center(a_transform), b.world_center(b_transform));
+    let relative_speed = |a: &RigidBody, b: &RigidBody, contact: &Contact| ((b.linear_velocity + b.angular_velocity.cross(contact.point - b_center))
+        - (a.linear_velocity + a.angular_velocity.cross(contact.point - a_center))).magnitude();
+    if contacts.iter().any(|contact| relative_speed(a, b, contact) > SLEEP_SPEED) {
+        if a.resting {
+            a.wake();
+        }
+        if b.resting {
+            b.wake();
+        }
+    }
+    let resting_speed = RESTING_SPEED + settings.gravity.magnitude() * time_step * 2.0;
+    let restitution = a.restitution.min(b.restitution);
+    let friction = (a.friction * b.friction).sqrt();
+    let a_inverse_inertia = if a.resting { Matrix3::zero() } else { a.world_inverse_inertia(a_transform) };
+    let b_inverse_inertia = if b.resting { Matrix3::zero() } else { b.world_inverse_inertia(b_transform) };
+    let a_inverse_mass = if a.resting { 0.0 } else { 1.0 / a.mass };
+    let b_inverse_mass = if b.resting { 0.0 } else { 1.0 / b.mass };
+    let effective_mass = |direction: Vector3<f32>, a_arm: Vector3<f32>, b_arm: Vector3<f32>| a_inverse_mass + b_inverse_mass
+        + direction.dot((a_inverse_inertia * a_arm.cross(direction)).cross(a_arm))
+        + direction.dot((b_inverse_inertia * b_arm.cross(direction)).cross(b_arm));
+    for _ in 0..CONTACT_ITERATIONS {
+        for contact in &contacts {
+            let (a_arm, b_arm) = (contact.point - a_center, contact.point - b_center);
+            let normal = contact.normal;
+            let velocity = (b.linear_velocity + b.angular_velocity.cross(b_arm)) - (a.linear_velocity + a.angular_velocity.cross(a_arm));
+            let closing = normal.dot(velocity);
+            if closing >= 0.0 {
+                continue;
+            }
+            let restitution = if -closing > resting_speed { restitution } else { 0.0 };
+            let normal_impulse = -(1.0 + restitution) * closing / effective_mass(normal, a_arm, b_arm);
+            a.push_scaled(a_inverse_mass, &a_inverse_inertia, -normal * normal_impulse, a_arm);
+            b.push_scaled(b_inverse_mass, &b_inverse_inertia, normal * normal_impulse, b_arm);
+            // coulomb friction opposes the bodies sliding across each other
+            let velocity = (b.linear_velocity + b.angular_velocity.cross(b_arm)) - (a.linear_velocity + a.angular_velocity.cross(a_arm));
+            let sliding = velocity - normal * normal.dot(velocity);
+            if sliding.magnitude2() < f32::EPSILON {
+                continue;
+            }
+            let tangent = sliding.normalize();
+            let limit = friction * normal_impulse;
+            let tangent_impulse = (-velocity.dot(tangent) / effective_mass(tangent, a_arm, b_arm)).max(-limit).min(limit);
+            a.push_scaled(a_inverse_mass, &a_inverse_inertia, -tangent * tangent_impulse, a_arm);
+            b.push_scaled(b_inverse_mass, &b_inverse_inertia, tangent * tangent_impulse, b_arm);
+        }
+    }
+    // push the bodies apart by their share of the inverse mass
+    let deepest = contacts.iter().fold(contacts[0], |deepest, contact| if contact.depth > deepest.depth { *contact } else { deepest });
+    let total_inverse_mass = a_inverse_mass + b_inverse_mass;
+    if deepest.depth > PENETRATION_SLOP && total_inverse_mass > 0.0 {
+        let correction = deepest.normal * ((deepest.depth - PENETRATION_SLOP) * PENETRATION_CORRECTION / total_inverse_mass);
+        a_transform.position_by((-correction * a_inverse_mass).into());
+        b_transform.position_by((correction * b_inverse_mass).into());
+    }
+}
+
+/// Finds where two convex hulls overlap, taking the separating axis of least overlap as the contact normal
+/// and the hull points lying inside the other hull as contact points
+fn find_contacts(a: &RigidBody, a_transform: &Transform, b: &RigidBody, b_transform: &Transform) -> Vec<Contact> {
+    let a_points = a.world_hull(a_transform);
+    let b_points = b.world_hull(b_transform);
+    let a_faces = a.world_faces(a_transform);
+    let b_faces = b.world_faces(b_transform);
+    let a_edges: Vec<Vector3<f32>> = a.edges.iter().map(|edge| Vector3::from(a_transform.transform_vector((*edge).into()))).collect();
+    let b_edges: Vec<Vector3<f32>> = b.edges.iter().map(|edge| Vector3::from(b_transform.transform_vector((*edge).into()))).collect();
+    let mut axes: Vec<Vector3<f32>> = a_faces.iter().chain(b_faces.iter()).map(|face| face.normal).collect();
+    for a_edge in &a_edges {
+        for b_edge in &b_edges {
+            let axis = a_edge.cross(*b_edge);
+            if axis.magnitude2() > f32::EPSILON {
+                axes.push(axis.normalize());
+            }
+        }
+    }
+    let project = |points: &[Vector3<f32>], axis: Vector3<f32>| points.iter()
+        .map(|point| point.dot(axis))
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(minimum, maximum), distance| (minimum.min(distance), maximum.max(distance)));
+    let mut normal = Vector3::zero();
+    let mut depth = f32::INFINITY;
+    for axis in axes {
+        let (a_minimum, a_maximum) = project(&a_points, axis);
+        let (b_minimum, b_maximum) = project(&b_points, axis);
+        let (forward, backward) = (a_maximum - b_minimum, b_maximum - a_minimum);
+        // any axis with a gap between the projections separates the hulls
+        if forward < -CONTACT_DISTANCE || backward < -CONTACT_DISTANCE {
+            return Vec::new();
+        }
+        if forward.min(backward) < depth {
+            depth = forward.min(backward);
+            normal = if forward < backward { axis } else { -axis };
+        }
+    }
+    let inside = |point: &Vector3<f32>, faces: &[Plane]| faces.iter().all(|face| face.distance((*point).into()) <= CONTACT_DISTANCE);
+    let a_deepest = project(&a_points, normal).1;
+    let b_deepest = project(&b_points, normal).0;
+    let mut contacts: Vec<Contact> = b_points.iter()
+        .filter(|point| inside(point, &a_faces))
+        .map(|point| Contact { point: *point, normal, depth: a_deepest - point.dot(normal) })
+        .chain(a_points.iter()
+            .filter(|point| inside(point, &b_faces))
+            .map(|point| Contact { point: *point, normal, depth: point.dot(normal) - b_deepest }))
+        .collect();
+    // crossed edges leave no point inside either hull, touch halfway between the deepest points
+    if contacts.is_empty() {
+        let support = |points: &[Vector3<f32>], direction: Vector3<f32>| points.iter()
+            .fold(points[0], |best, point| if point.dot(direction) > best.dot(direction) { *point } else { best });
+        let point = (support(&a_points, normal) + support(&b_points, -normal)) * 0.5;
+        contacts.push(Contact { point, normal, depth });
+    }
+    contacts
+}
+
+/// Finds the pairs of boxes that overlap by sweeping along x, in a stable order
+/// takes   boxes as [Bounds]
+/// returns indices of overlapping boxes, lower index first, as Vec<(usize, usize)>
+pub fn overlapping_pairs(bounds: &[Bounds]) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..bounds.len()).collect();
+    order.sort_by(|a, b| bounds[*a].0[0].partial_cmp(&bounds[*b].0[0]).unwrap_or(std::cmp::Ordering::Equal));
+    let mut pairs = Vec::new();
+    for (position, a) in order.iter().enumerate() {
+        for b in &order[position + 1..] {
+            // boxes further along start past the end of this one
+            if bounds[*b].0[0] > bounds[*a].1[0] + CONTACT_DISTANCE {
+                break;
+            }
+            let overlapping = (1..3).all(|axis| bounds[*a].0[axis] <= bounds[*b].1[axis] + CONTACT_DISTANCE
+                && bounds[*b].0[axis] <= bounds[*a].1[axis] + CONTACT_DISTANCE);
+            if overlapping {
+                pairs.push((*a.min(b), *a.max(b)));
+            }
+        }
+    }
+    pairs.sort();
+    pairs
+}
+
+/// Measures a closed triangle mesh as a solid of unit density
+/// takes   vertex positions as [Vector3<f32>]
+///         outward wound triangles as [[usize; 3]]
+/// returns volume, center of mass and inertia tensor about the center as (f32, Vector3<f32>, Matrix3<f32>)
+pub fn mass_properties(positions: &[Vector3<f32>], triangles: &[[usize; 3]]) -> (f32, Vector3<f32>, Matrix3<f32>) {
+    // sum tetrahedra between the origin and each triangle
+    let mut determinant_total = 0.0;
+    let mut weighted_center = Vector3::zero();
+    let mut covariance = Matrix3::zero();
+    for triangle in triangles {
+        let a = positions[triangle[0]];
+        let b = positions[triangle[1]];
+        let c = positions[triangle[2]];
+        let determinant = a.dot(b.cross(c));
+        let sum = a + b + c;
+        determinant_total += determinant;
+        weighted_center += sum * determinant;
+        covariance += (outer(a, a) + outer(b, b) + outer(c, c) + outer(sum, sum)) * (determinant / 120.0);
+    }
+    let volume = determinant_total / 6.0;
+    let center = weighted_center / (determinant_total * 4.0);
+    let covariance = covariance - outer(center, center) * volume;
+    let trace = covariance.x.x + covariance.y.y + covariance.z.z;
+    (volume, center, Matrix3::identity() * trace - covariance)
+}
+
+fn outer(a: Vector3<f32>, b: Vector3<f32>) -> Matrix3<f32> {
+    Matrix3::from_cols(a * b.x, a * b.y, a * b.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::ShapeLibrary;
+
+    #[test]
+    fn only_the_table_floor_supports_bodies() {
+        let settings = PhysicsSettings::table([2.0, 2.0]);
+        let supporting: Vec<bool> = settings.get_planes().iter().map(|plane| settings.supports(plane)).collect();
+        assert_eq!(supporting, vec![true, false, false, false, false]);
+    }
+
+    #[test]
+    fn a_dropped_cube_sleeps_lying_flat_on_the_floor() {
+        let shapes = ShapeLibrary::load("assets");
+        let settings = PhysicsSettings::table([2.0, 2.0]);
+        let mut body = RigidBody::new(shapes.get_shape("hexahedron"), &Transform::identity(), 1.0, 0.4, 0.5);
+        let mut transform = Transform::new([0.0, 0.0, -1.0], [1.0, 0.0, 0.0, 0.0], [1.0, 1.0, 1.0]);
+        for _ in 0..600 {
+            body.step(&mut transform, &settings, 0.01);
+        }
+        assert!(body.is_resting());
+        let on_floor = body.get_hull().iter()
+            .filter(|point| transform.transform_point((**point).into())[2] > -CONTACT_DISTANCE)
+            .count();
+        assert!(on_floor >= 4, "{} corners on the floor", on_floor);
+    }
+}
@@ -0,0 +1,154 @@
+use cgmath::{
+    InnerSpace,
+    Matrix4,
+    SquareMatrix,
+    Vector3,
+    Vector4
+};
+
+use crate::{
+    entity::{
+        Entities,
+        Entity
+    },
+    gfx::GraphicLibrary,
+    scene::Scene
+};
+
+#[derive(Debug, Copy, Clone)]
+pub struct Ray {
+    origin: Vector3<f32>,
+    direction: Vector3<f32>
+}
+#[allow(dead_code)]
+impl Ray {
+    pub fn new(origin: [f32; 3], direction: [f32; 3]) -> Ray {
+        Ray {
+            origin: origin.into(),
+            direction: Vector3::from(direction).normalize()
+        }
+    }
+    /// Constructs world space ray through a window position
+    /// takes   cursor position in window pixels as [f32; 2]
+    ///         window size in pixels as [f32; 2]
+    ///         view transformation as [[f32; 4]; 4]
+    ///         perspective transformation as [[f32; 4]; 4]
+    /// returns ray from the near plane into the scene as Option<Ray>
+    pub fn from_cursor(cursor: [f32; 2], dimensions: [f32; 2], view: [[f32; 4]; 4], perspective: [[f32; 4]; 4]) -> Option<Ray> {
+        let x = cursor[0] / dimensions[0] * 2.0 - 1.0;
+        let y = 1.0 - cursor[1] / dimensions[1] * 2.0;
+        let inverse = (Matrix4::from(perspective) * Matrix4::from(view)).invert()?;
+        let unproject = |z: f32| {
+            let point = inverse * Vector4::new(x, y, z, 1.0);
+            point.truncate() / point.w
+        };
+        let near = unproject(-1.0);
+        let far = unproject(1.0);
+        Some(Ray {
+            origin: near,
+            direction: (far - near).normalize()
+        })
+    }
+    pub fn get_origin(&self) -> [f32; 3] {
+        self.origin.into()
+    }
+    pub fn get_direction(&self) -> [f32; 3] {
+        self.direction.into()
+    }
+    pub fn point_at(&self, distance: f32) -> [f32; 3] {
+        (self.origin + self.direction * distance).into()
+    }
+    /// Moves ray into the space of a transformation, direction is left unnormalized so
+    /// distances along the returned ray match distances along this one
+    /// takes   transformation as Matrix4<f32>
+    /// returns transformed ray as Ray
+    pub fn transformed(&self, matrix: &Matrix4<f32>) -> Ray {
+        Ray {
+            origin: (matrix * self.origin.extend(1.0)).truncate(),
+            direction: (matrix * self.direction.extend(0.0)).truncate()
+        }
+    }
+    /// Intersects ray against a sphere
+    /// takes   sphere center as Vector3<f32>
+    ///         sphere radius as f32
+    /// returns distance to nearest intersection in front of the origin as Option<f32>
+    pub fn intersect_sphere(&self, center: Vector3<f32>, radius: f32) -> Option<f32> {
+        let offset = self.origin - center;
+        let a = self.direction.magnitude2();
+        let b = offset.dot(self.direction);
+        let c = offset.magnitude2() - radius * radius;
+        let discriminant = b * b - a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        let near = (-b - root) / a;
+        let far = (-b + root) / a;
+        if far < 0.0 {
+            None
+        } else {
+            Some(near.max(0.0))
+        }
+    }
+    /// Intersects ray against both sides of a triangle
+    /// takes   triangle corners as Vector3<f32>
+    /// returns distance to intersection in front of the origin as Option<f32>
+    pub fn intersect_triangle(&self, a: Vector3<f32>, b: Vector3<f32>, c: Vector3<f32>) -> Option<f32> {
+        let edge1 = b - a;
+        let edge2 = c - a;
+        let p = self.direction.cross(edge2);
+        let determinant = edge1.dot(p);
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+        let inverse = 1.0 / determinant;
+        let t = self.origin - a;
+        let u = t.dot(p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = t.cross(edge1);
+        let v = self.direction.dot(q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let distance = edge2.dot(q) * inverse;
+        if distance < 0.0 {
+            None
+        } else {
+            Some(distance)
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PickHit {
+    pub entity: Entity,
+    pub face: usize,
+    pub point: [f32; 3],
+    pub distance: f32
+}
+
+/// Finds the nearest entity under a ray, of those the render system has drawn
+/// takes   world space ray as Ray
+///         entities to test as Entities
+///         scene holding the entity nodes as Scene
+///         graphic library holding the entity meshes as GraphicLibrary
+/// returns nearest hit with the entity as Option<PickHit>
+pub fn pick_entities(ray: &Ray, entities: &Entities, scene: &Scene, library: &GraphicLibrary) -> Option<PickHit> {
+    let mut nearest: Option<PickHit> = None;
+    for entity in entities.iter() {
+        let hit = entities.get_node(entity).and_then(|node| scene.pick(ray, node, library));
+        if let Some((face, point, distance)) = hit {
+            if nearest.is_none_or(|hit| distance < hit.distance) {
+                nearest = Some(PickHit {
+                    entity,
+                    face,
+                    point,
+                    distance
+                });
+            }
+        }
+    }
+    nearest
+}
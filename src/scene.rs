@@ -0,0 +1,316 @@
+use crate::{
+    etc::{
+        infinite_projection,
+        orthographic_projection,
+        perspective_projection
+    },
+    gfx::{
+        Graphic,
+        GraphicLibrary,
+        RenderQueue
+    },
+    pick::Ray,
+    transform::Transform
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SceneLight {
+    color: [f32; 3]
+}
+#[allow(dead_code)]
+impl SceneLight {
+    pub fn new(color: [f32; 3]) -> SceneLight {
+        SceneLight {
+            color
+        }
+    }
+    pub fn get_color(&self) -> [f32; 3] {
+        self.color
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CameraProjection {
+    Perspective { fov: f32 },
+    InfinitePerspective { fov: f32 },
+    Orthographic { view_height: f32 }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SceneCamera {
+    projection: CameraProjection,
+    near: f32,
+    far: f32
+}
+#[allow(dead_code)]
+impl SceneCamera {
+    pub fn new(fov: f32, near: f32, far: f32) -> SceneCamera {
+        SceneCamera {
+            projection: CameraProjection::Perspective { fov },
+            near,
+            far
+        }
+    }
+    /// Constructs a perspective camera without a far plane
+    /// takes   vertical field of view in radians as f32
+    ///         near plane distance as f32
+    /// returns camera as SceneCamera
+    pub fn infinite(fov: f32, near: f32) -> SceneCamera {
+        SceneCamera {
+            projection: CameraProjection::InfinitePerspective { fov },
+            near,
+            far: f32::INFINITY
+        }
+    }
+    pub fn orthographic(view_height: f32, near: f32, far: f32) -> SceneCamera {
+        SceneCamera {
+            projection: CameraProjection::Orthographic { view_height },
+            near,
+            far
+        }
+    }
+    pub fn get_projection_mode(&self) -> CameraProjection {
+        self.projection
+    }
+    /// Constructs the projection transformation of the camera
+    /// takes   viewport width and height as f32
+    /// returns projection transformation as [[f32; 4]; 4]
+    pub fn get_projection(&self, width: f32, height: f32) -> [[f32; 4]; 4] {
+        match self.projection {
+            CameraProjection::Perspective { fov } => perspective_projection(width, height, fov, self.near, self.far),
+            CameraProjection::InfinitePerspective { fov } => infinite_projection(width, height, fov, self.near),
+            CameraProjection::Orthographic { view_height } => orthographic_projection(width, height, view_height, self.near, self.far)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SceneNode {
+    local: Transform,
+    world: Transform,
+    dirty: bool,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    graphic: Option<Graphic>,
+    light: Option<SceneLight>,
+    camera: Option<SceneCamera>
+}
+#[allow(dead_code)]
+impl SceneNode {
+    pub fn get_parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+    pub fn get_children(&self) -> &[NodeId] {
+        &self.children
+    }
+    pub fn get_graphic(&self) -> Option<&Graphic> {
+        self.graphic.as_ref()
+    }
+    pub fn get_light(&self) -> Option<&SceneLight> {
+        self.light.as_ref()
+    }
+    pub fn get_camera(&self) -> Option<&SceneCamera> {
+        self.camera.as_ref()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Scene {
+    nodes: Vec<Option<SceneNode>>,
+    free: Vec<usize>
+}
+#[allow(dead_code)]
+impl Scene {
+    pub fn new() -> Scene {
+        Scene {
+            nodes: Vec::new(),
+            free: Vec::new()
+        }
+    }
+    /// Adds a node, nodes without a parent are placed in world space
+    /// takes   parent node as Option<NodeId>
+    ///         transform relative to the parent as Transform
+    /// returns id of the new node as NodeId
+    pub fn add_node(&mut self, parent: Option<NodeId>, local: Transform) -> NodeId {
+        let node = SceneNode {
+            local,
+            world: local,
+            dirty: true,
+            parent: None,
+            children: Vec::new(),
+            graphic: None,
+            light: None,
+            camera: None
+        };
+        let id = match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = Some(node);
+                NodeId(index)
+            },
+            None => {
+                self.nodes.push(Some(node));
+                NodeId(self.nodes.len() - 1)
+            }
+        };
+        if parent.is_some() {
+            self.set_parent(id, parent);
+        }
+        id
+    }
+    /// Removes a node along with all of its children
+    /// takes   node as NodeId
+    pub fn remove_node(&mut self, id: NodeId) {
+        self.detach(id);
+        let mut stack = vec![id];
+        while let Some(NodeId(index)) = stack.pop() {
+            if let Some(node) = self.nodes[index].take() {
+                stack.extend(node.children);
+                self.free.push(index);
+            }
+        }
+    }
+    pub fn get_node(&self, id: NodeId) -> &SceneNode {
+        match self.nodes.get(id.0).and_then(Option::as_ref) {
+            Some(node) => node,
+            None => panic!("Could not find node {} in scene", id.0)
+        }
+    }
+    fn get_node_mut(&mut self, id: NodeId) -> &mut SceneNode {
+        match self.nodes.get_mut(id.0).and_then(Option::as_mut) {
+            Some(node) => node,
+            None => panic!("Could not find node {} in scene", id.0)
+        }
+    }
+    /// Moves a node under a new parent, keeping its local transform
+    /// takes   node as NodeId
+    ///         new parent as Option<NodeId>
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) {
+        // walking up from the new parent must not reach the node itself
+        let mut ancestor = parent;
+        while let Some(ancestor_id) = ancestor {
+            if ancestor_id == id {
+                panic!("Could not parent node {} to its own descendant", id.0);
+            }
+            ancestor = self.get_node(ancestor_id).parent;
+        }
+        self.detach(id);
+        if let Some(parent_id) = parent {
+            self.get_node_mut(parent_id).children.push(id);
+        }
+        let node = self.get_node_mut(id);
+        node.parent = parent;
+        node.dirty = true;
+    }
+    fn detach(&mut self, id: NodeId) {
+        if let Some(parent_id) = self.get_node(id).parent {
+            self.get_node_mut(parent_id).children.retain(|child| *child != id);
+            self.get_node_mut(id).parent = None;
+        }
+    }
+    pub fn get_local(&self, id: NodeId) -> &Transform {
+        &self.get_node(id).local
+    }
+    /// Replaces the local transform of a node, its world transform and those of its children
+    /// are refreshed by the next update
+    /// takes   node as NodeId
+    ///         transform relative to the parent as Transform
+    pub fn set_local(&mut self, id: NodeId, local: Transform) {
+        let node = self.get_node_mut(id);
+        node.local = local;
+        node.dirty = true;
+    }
+    /// Gets the world transform as of the last update
+    /// takes   node as NodeId
+    /// returns world transform as Transform
+    pub fn get_world(&self, id: NodeId) -> &Transform {
+        &self.get_node(id).world
+    }
+    pub fn attach_graphic(&mut self, id: NodeId, graphic: Graphic) {
+        self.get_node_mut(id).graphic = Some(graphic);
+    }
+    pub fn attach_light(&mut self, id: NodeId, light: SceneLight) {
+        self.get_node_mut(id).light = Some(light);
+    }
+    pub fn attach_camera(&mut self, id: NodeId, camera: SceneCamera) {
+        self.get_node_mut(id).camera = Some(camera);
+    }
+    /// Recomputes world transforms of dirty nodes and everything below them
+    pub fn update(&mut self) {
+        let roots: Vec<NodeId> = self.nodes.iter()
+            .enumerate()
+            .filter_map(|(index, node)| match node {
+                Some(node) if node.parent.is_none() => Some(NodeId(index)),
+                _ => None
+            })
+            .collect();
+        let mut stack: Vec<(NodeId, Option<Transform>)> = roots.into_iter().map(|id| (id, None)).collect();
+        // parent world transform is passed down only when it changed
+        while let Some((id, parent_world)) = stack.pop() {
+            let node = self.get_node(id);
+            let parent_world = match parent_world {
+                Some(parent_world) => Some(parent_world),
+                None if node.dirty => Some(node.parent.map_or(Transform::identity(), |parent| *self.get_world(parent))),
+                None => None
+            };
+            let node = self.get_node_mut(id);
+            if let Some(parent_world) = parent_world {
+                node.world = parent_world.compose(&node.local);
+            }
+            node.dirty = false;
+            let world = parent_world.map(|_| node.world);
+            for child in node.children.clone() {
+                stack.push((child, world));
+            }
+        }
+    }
+    /// Queues every graphic in the scene at its world transform
+    /// takes   render queue as RenderQueue
+    ///         graphic library as GraphicLibrary
+    pub fn queue(&mut self, queue: &mut RenderQueue, library: &GraphicLibrary) {
+        for node in self.nodes.iter_mut().flatten() {
+            if let Some(graphic) = node.graphic.as_mut() {
+                graphic.queue(queue, library, &node.world);
+            }
+        }
+    }
+    /// Intersects a world space ray against the graphic of a node
+    /// takes   world space ray as Ray
+    ///         node as NodeId
+    ///         graphic library as GraphicLibrary
+    /// returns face index, world space hit point and world distance as Option<(usize, [f32;3], f32)>
+    pub fn pick(&self, ray: &Ray, id: NodeId, library: &GraphicLibrary) -> Option<(usize, [f32; 3], f32)> {
+        let node = self.get_node(id);
+        node.graphic.as_ref()?.pick(ray, library, &node.world)
+    }
+    /// Finds the direction of the first light, lights shine along their +z axis
+    /// returns light direction as Option<[f32; 3]>
+    pub fn get_light_direction(&self) -> Option<[f32; 3]> {
+        self.nodes.iter()
+            .flatten()
+            .find(|node| node.light.is_some())
+            .map(|node| node.world.transform_vector([0.0, 0.0, 1.0]))
+    }
+    /// Constructs the view transformation of a camera node, cameras look along their +z axis
+    /// takes   camera node as NodeId
+    /// returns view transformation as [[f32; 4]; 4]
+    pub fn get_view(&self, id: NodeId) -> [[f32; 4]; 4] {
+        self.get_node(id).world.inverse().to_matrix().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cameras_use_the_projection_of_their_mode() {
+        let fov = std::f32::consts::FRAC_PI_3;
+        assert_eq!(SceneCamera::new(fov, 0.1, 100.0).get_projection(16.0, 9.0), perspective_projection(16.0, 9.0, fov, 0.1, 100.0));
+        assert_eq!(SceneCamera::infinite(fov, 0.1).get_projection(16.0, 9.0), infinite_projection(16.0, 9.0, fov, 0.1));
+        assert_eq!(SceneCamera::orthographic(3.0, 0.1, 16.0).get_projection(16.0, 9.0), orthographic_projection(16.0, 9.0, 3.0, 0.1, 16.0));
+    }
+}
@@ -0,0 +1,149 @@
+use obj::{
+    load_obj, Obj,
+    TexturedVertex
+};
+use std::{
+    collections::HashMap,
+    fs
+};
+
+use crate::etc::load_bytes;
+
+/// Value shown by a die when a mesh space direction points up, a face normal for most dice
+/// and a vertex direction for the d4
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FaceValue {
+    pub direction: [f32; 3],
+    pub value: u32
+}
+
+// mesh space direction a model faces when its manifest gives no forward axis
+const DEFAULT_FORWARD: [f32; 3] = [1.0, 0.0, 0.0];
+
+/// Mesh geometry without any graphical data, usable without a display
+#[derive(Debug, Clone)]
+pub struct Shape {
+    positions: Vec<[f32; 3]>,
+    triangles: Vec<[usize; 3]>,
+    face_values: Vec<FaceValue>,
+    forward: [f32; 3]
+}
+#[allow(dead_code)]
+impl Shape {
+    pub fn from_path(object_file_path: &str) -> Shape {
+        let buffer = load_bytes(object_file_path);
+        let obj: Obj<TexturedVertex> = load_obj(&buffer[..]).unwrap();
+        // vertices split along uv seams are welded back together by position
+        let mut positions: Vec<[f32; 3]> = Vec::new();
+        let mut welded = Vec::with_capacity(obj.vertices.len());
+        for vertex in &obj.vertices {
+            match positions.iter().position(|position| *position == vertex.position) {
+                Some(index) => welded.push(index),
+                None => {
+                    welded.push(positions.len());
+                    positions.push(vertex.position);
+                }
+            }
+        }
+        let triangles = obj.indices.chunks(3)
+            .map(|face| [welded[face[0] as usize], welded[face[1] as usize], welded[face[2] as usize]])
+            .collect();
+        Shape {
+            positions,
+            triangles,
+            face_values: Vec::new(),
+            forward: DEFAULT_FORWARD
+        }
+    }
+    /// Reads face values from a manifest
+    /// takes   manifest face_values array, each entry holding a direction and a value, as serde_json::Value
+    pub fn set_face_values(&mut self, face_values: &serde_json::Value) {
+        self.face_values = face_values.as_array().map_or(Vec::new(), |entries| entries.iter()
+            .map(|entry| {
+                let direction = &entry["direction"];
+                FaceValue {
+                    direction: [
+                        direction[0].as_f64().unwrap() as f32,
+                        direction[1].as_f64().unwrap() as f32,
+                        direction[2].as_f64().unwrap() as f32
+                    ],
+                    value: entry["value"].as_u64().unwrap() as u32
+                }
+            })
+            .collect());
+    }
+    /// Sets the mesh space direction the model faces, exporters disagree on which axis that is
+    /// takes   forward axis as [f32; 3]
+    pub fn set_forward(&mut self, forward: [f32; 3]) {
+        self.forward = forward;
+    }
+    pub fn get_forward(&self) -> [f32; 3] {
+        self.forward
+    }
+    pub fn get_face_values(&self) -> &[FaceValue] {
+        &self.face_values
+    }
+    pub fn get_positions(&self) -> &[[f32; 3]] {
+        &self.positions
+    }
+    pub fn get_triangles(&self) -> &[[usize; 3]] {
+        &self.triangles
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ShapeLibrary {
+    shape_dict: HashMap<String, Shape>
+}
+#[allow(dead_code)]
+impl ShapeLibrary {
+    pub fn new() -> ShapeLibrary {
+        ShapeLibrary {
+            shape_dict: HashMap::new()
+        }
+    }
+    /// Loads the shapes of every manifest in a folder, the same manifests the graphic library reads
+    /// takes   folder path as str
+    /// returns shape library as ShapeLibrary
+    pub fn load(file_path: &str) -> ShapeLibrary {
+        let mut library = ShapeLibrary::new();
+        library.load_path(file_path);
+        library
+    }
+    pub fn load_path(&mut self, file_path: &str) {
+        let paths = fs::read_dir(file_path).unwrap();
+        for path in paths {
+            let file = path.unwrap().path();
+            let extension = file.extension().and_then(std::ffi::OsStr::to_str).unwrap();
+            match extension {
+                "json" => self.load_json(file.to_str().unwrap()),
+                _=> continue
+            }
+        }
+    }
+    pub fn load_json(&mut self, json_file_path: &str) {
+        let buffer: serde_json::Value = serde_json::from_slice(&load_bytes(json_file_path)).unwrap();
+        if buffer["object_key"].is_null() {
+            return;
+        }
+        let mut shape = Shape::from_path(buffer["object_file_path"].to_string().trim_matches('"'));
+        shape.set_face_values(&buffer["face_values"]);
+        if let Some(forward) = buffer["forward"].as_array() {
+            shape.set_forward([
+                forward[0].as_f64().unwrap() as f32,
+                forward[1].as_f64().unwrap() as f32,
+                forward[2].as_f64().unwrap() as f32
+            ]);
+        }
+        self.shape_dict.insert(
+            buffer["object_key"].to_string().trim_matches('"').to_string(),
+            shape
+        );
+    }
+    pub fn get_shape(&self, shape_key: &str) -> &Shape {
+        match self.shape_dict.get(shape_key) {
+            Some(shape) => shape,
+            None => panic!("Could not find shape key '{}' in library", shape_key)
+        }
+    }
+}
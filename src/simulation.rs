@@ -0,0 +1,400 @@
+use rand::{
+    Rng,
+    rngs::StdRng,
+    SeedableRng
+};
+use serde_json::json;
+use std::collections::VecDeque;
+
+use crate::{
+    entity::{
+        movement_system,
+        physics_system,
+        Entities,
+        Entity
+    },
+    obstacle::CarvedNavMesh,
+    physics::PhysicsSettings
+};
+
+// most agents planning their paths again in one tick, the rest wait for later ticks
+const REPLANS_PER_TICK: usize = 4;
+
+/// Impulse applied to a die, recorded with the tick it was applied before
+///
+/// Dice are numbered in the order their entities were spawned counting only entities with rigid
+/// bodies, so other entities spawned before or between them don't change which die a record throws
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Throw {
+    pub tick: u64,
+    pub die: usize,
+    pub impulse: [f32; 3],
+    pub point: [f32; 3]
+}
+
+/// Everything needed to reproduce a roll, the seed and every impulse applied to the dice
+#[derive(Debug, Clone, PartialEq)]
+pub struct RollRecord {
+    seed: u64,
+    throws: Vec<Throw>
+}
+#[allow(dead_code)]
+impl RollRecord {
+    pub fn new(seed: u64) -> RollRecord {
+        RollRecord {
+            seed,
+            throws: Vec::new()
+        }
+    }
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+    pub fn get_throws(&self) -> &[Throw] {
+        &self.throws
+    }
+    /// Reads a record written by save
+    /// takes   json file path as str
+    /// returns roll record, or what is wrong with the file, as Result<RollRecord, String>
+    pub fn load(json_file_path: &str) -> Result<RollRecord, String> {
+        let bytes = std::fs::read(json_file_path).map_err(|why| format!("Couldn't read {}: {}", json_file_path, why))?;
+        let buffer: serde_json::Value = serde_json::from_slice(&bytes)
+            .map_err(|why| format!("Couldn't parse {}: {}", json_file_path, why))?;
+        let invalid = |field: &str| format!("Couldn't read {}: missing or invalid {}", json_file_path, field);
+        let vector = |value: &serde_json::Value, field: &str| -> Result<[f32; 3], String> {
+            let component = |index: usize| value[index].as_f64().map(|component| component as f32).ok_or_else(|| invalid(field));
+            Ok([component(0)?, component(1)?, component(2)?])
+        };
+        let throws = buffer["throws"].as_array().ok_or_else(|| invalid("throws"))?.iter()
+            .map(|throw| Ok(Throw {
+                tick: throw["tick"].as_u64().ok_or_else(|| invalid("tick"))?,
+                // saved as body, rolls recorded before entities counted only dice as bodies
+                die: throw["body"].as_u64().ok_or_else(|| invalid("body"))? as usize,
+                impulse: vector(&throw["impulse"], "impulse")?,
+                point: vector(&throw["point"], "point")?
+            }))
+            .collect::<Result<Vec<Throw>, String>>()?;
+        Ok(RollRecord {
+            // the seed is stored as a string, json numbers cannot hold every u64
+            seed: buffer["seed"].as_str().and_then(|seed| seed.parse().ok()).ok_or_else(|| invalid("seed"))?,
+            throws
+        })
+    }
+    /// Writes the record as json, f32 values survive the round trip exactly
+    /// takes   json file path as str
+    pub fn save(&self, json_file_path: &str) {
+        let throws: Vec<serde_json::Value> = self.throws.iter()
+            .map(|throw| json!({
+                "tick": throw.tick,
+                "body": throw.die,
+                "impulse": throw.impulse,
+                "point": throw.point
+            }))
+            .collect();
+        let buffer = json!({
+            "seed": self.seed.to_string(),
+            "throws": throws
+        });
+        if let Err(why) = std::fs::write(json_file_path, serde_json::to_vec_pretty(&buffer).unwrap()) {
+            panic!("Couldn't write {}: {}", json_file_path, why);
+        }
+    }
+}
+
+/// Fixed tick driver of the entities' systems owning the random number generator
+///
+/// The same seed and the same throws at the same ticks give bit identical entity trajectories on the
+/// same build, randomness must come from this generator and time only from the tick count
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    rng: StdRng,
+    tick: u64,
+    record: RollRecord,
+    replaying: bool,
+    replans: VecDeque<Entity>,
+    replans_per_tick: usize
+}
+#[allow(dead_code)]
+impl Simulation {
+    pub fn new(seed: u64) -> Simulation {
+        Simulation {
+            rng: StdRng::seed_from_u64(seed),
+            tick: 0,
+            record: RollRecord::new(seed),
+            replaying: false,
+            replans: VecDeque::new(),
+            replans_per_tick: REPLANS_PER_TICK
+        }
+    }
+    /// Constructs a simulation playing back a record, throws made while replaying are ignored
+    /// takes   roll record as RollRecord
+    /// returns simulation as Simulation
+    pub fn replay(record: RollRecord) -> Simulation {
+        Simulation {
+            rng: StdRng::seed_from_u64(record.seed),
+            tick: 0,
+            record,
+            replaying: true,
+            replans: VecDeque::new(),
+            replans_per_tick: REPLANS_PER_TICK
+        }
+    }
+    pub fn get_seed(&self) -> u64 {
+        self.record.seed
+    }
+    pub fn get_tick(&self) -> u64 {
+        self.tick
+    }
+    pub fn get_record(&self) -> &RollRecord {
+        &self.record
+    }
+    pub fn is_replaying(&self) -> bool {
+        self.replaying
+    }
+    pub fn get_rng_mut(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+    /// Limits how many agents plan their paths again each tick, the rest wait their turn
+    /// takes   replans per tick as usize
+    pub fn set_replans_per_tick(&mut self, replans_per_tick: usize) {
+        self.replans_per_tick = replans_per_tick;
+    }
+    pub fn get_replans_per_tick(&self) -> usize {
+        self.replans_per_tick
+    }
+    /// Pushes a die before the next tick and records the push, entities without rigid bodies
+    /// can't be thrown
+    /// takes   entities as Entities
+    ///         entity with a rigid body as Entity
+    ///         impulse as [f32; 3]
+    ///         point the impulse acts at as [f32; 3]
+    pub fn throw(&mut self, entities: &mut Entities, entity: Entity, impulse: [f32; 3], point: [f32; 3]) {
+        if self.replaying {
+            return;
+        }
+        let die = match die_index(entities, entity) {
+            Some(die) => die,
+            None => return
+        };
+        entities.apply_impulse(entity, impulse, point);
+        self.record.throws.push(Throw {
+            tick: self.tick,
+            die,
+            impulse,
+            point
+        });
+    }
+    /// Throws an entity with a random impulse off its centre, setting it spinning
+    /// takes   entities as Entities
+    ///         entity with a rigid body as Entity
+    pub fn random_throw(&mut self, entities: &mut Entities, entity: Entity) {
+        let impulse = [self.rng.gen::<f32>() * 2.0 - 1.0, self.rng.gen::<f32>() * 2.0 - 1.0, -self.rng.gen::<f32>() * 2.0];
+        let position = entities.get_transform(entity).get_position();
+        let point = [position[0] + self.rng.gen::<f32>() * 0.1 - 0.05, position[1] + self.rng.gen::<f32>() * 0.1 - 0.05, position[2]];
+        self.throw(entities, entity, impulse, point);
+    }
+    /// Advances every entity by one tick, first applying any recorded throws due when replaying and
+    /// carving changed obstacles, queueing agents whose paths they block to plan again, then running
+    /// the movement and physics systems
+    /// takes   entities as Entities
+    ///         navigation mesh with obstacles as CarvedNavMesh
+    ///         physics settings as PhysicsSettings
+    ///         time step in seconds as f32
+    pub fn step(&mut self, entities: &mut Entities, carved_nav_mesh: &mut CarvedNavMesh, physics: &PhysicsSettings, time_step: f32) {
+        entities.begin_tick();
+        if self.replaying {
+            for throw in self.record.throws.iter().filter(|throw| throw.tick == self.tick) {
+                match die_entity(entities, throw.die) {
+                    Some(entity) => entities.apply_impulse(entity, throw.impulse, throw.point),
+                    None => panic!("Could not find die {} thrown at tick {} of the replay", throw.die, throw.tick)
+                }
+            }
+        }
+        let blocked = carved_nav_mesh.update();
+        if !blocked.is_empty() {
+            for (entity, agent) in entities.get_agents().iter() {
+                if agent.path_crosses(entities.get_transform(entity), &blocked) && !self.replans.contains(&entity) {
+                    self.replans.push_back(entity);
+                }
+            }
+        }
+        let nav_mesh = carved_nav_mesh.get_nav_mesh();
+        for _ in 0..self.replans_per_tick.min(self.replans.len()) {
+            let entity = self.replans.pop_front().unwrap();
+            if let Some((agent, transform)) = entities.get_agent_and_transform_mut(entity) {
+                agent.replan(nav_mesh, transform);
+            }
+        }
+        for entity in self.replans.iter() {
+            if let Some((agent, transform)) = entities.get_agent_and_transform_mut(*entity) {
+                agent.wait_for_replan(transform);
+            }
+        }
+        movement_system(entities, nav_mesh, physics, &mut self.rng, time_step);
+        physics_system(entities, physics, time_step);
+        self.tick += 1;
+    }
+}
+
+// position of an entity among the entities with rigid bodies
+fn die_index(entities: &Entities, entity: Entity) -> Option<usize> {
+    entities.get_rigid_bodies().iter().position(|(die, _)| die == entity)
+}
+
+// entity of the die at a position among the entities with rigid bodies
+fn die_entity(entities: &Entities, die: usize) -> Option<Entity> {
+    entities.get_rigid_bodies().iter().nth(die).map(|(entity, _)| entity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::{
+            NavAgent,
+            NavStatus
+        },
+        navigation::build_nav_mesh,
+        obstacle::{
+            Obstacle,
+            ObstacleShape
+        },
+        physics::RigidBody,
+        shape::ShapeLibrary,
+        transform::Transform
+    };
+
+    const TIME_STEP: f32 = 0.016;
+
+    // three dice above a flat table and an agent walking across it
+    fn table() -> (Entities, CarvedNavMesh, PhysicsSettings) {
+        let shapes = ShapeLibrary::load("assets");
+        let nav_mesh = build_nav_mesh(&[[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [1.0, 1.0, 0.0], [-1.0, 1.0, 0.0]], &[[0, 1, 2], [0, 2, 3]]);
+        let mesh = Transform::new([0.0, 0.0, 0.0], [1.0, 0.0, 0.0, 0.0], [0.25, 0.25, 0.25]);
+        let mut entities = Entities::new();
+        for (index, object_key) in ["tetrahedron", "hexahedron", "icosahedron"].iter().enumerate() {
+            let die = entities.spawn(Transform::new([index as f32 * 0.4 - 0.4, 0.0, -0.5], [1.0, 0.0, 0.0, 0.0], [1.0, 1.0, 1.0]));
+            entities.insert_rigid_body(die, RigidBody::new(shapes.get_shape(object_key), &mesh, 1.0, 0.4, 0.5));
+        }
+        let walker = entities.spawn(Transform::new([-0.8, -0.8, 0.0], [1.0, 0.0, 0.0, 0.0], [1.0, 1.0, 1.0]));
+        let mut agent = NavAgent::new([0.0, 0.0, 0.0]);
+        agent.set_waypoint(&nav_mesh, entities.get_transform(walker), [0.8, 0.8, 0.0].into());
+        entities.insert_agent(walker, agent);
+        (entities, CarvedNavMesh::new(&nav_mesh, 0.1), PhysicsSettings::table([2.0, 2.0]))
+    }
+
+    // a row of walkers heading across the table
+    fn walkers(nav_mesh: &CarvedNavMesh) -> Entities {
+        let mut entities = Entities::new();
+        for row in 0..6 {
+            let y = row as f32 * 0.2 - 0.5;
+            let walker = entities.spawn(Transform::new([-0.8, y, 0.0], [1.0, 0.0, 0.0, 0.0], [1.0, 1.0, 1.0]));
+            let mut agent = NavAgent::new([0.0, 0.0, 0.0]);
+            agent.set_waypoint(nav_mesh.get_nav_mesh(), entities.get_transform(walker), [0.8, y, 0.0].into());
+            entities.insert_agent(walker, agent);
+        }
+        entities
+    }
+
+    fn run(mut simulation: Simulation, ticks: u64) -> (Vec<Transform>, RollRecord) {
+        let (mut entities, mut nav_mesh, physics) = table();
+        let dice: Vec<Entity> = entities.get_rigid_bodies().iter().map(|(entity, _)| entity).collect();
+        for die in dice.iter() {
+            simulation.random_throw(&mut entities, *die);
+        }
+        for _ in 0..ticks {
+            if simulation.get_tick() == 40 {
+                let point = entities.get_transform(dice[1]).get_position();
+                simulation.throw(&mut entities, dice[1], [0.3, -0.2, -1.0], point);
+            }
+            simulation.step(&mut entities, &mut nav_mesh, &physics, TIME_STEP);
+        }
+        let transforms = entities.iter().map(|entity| *entities.get_transform(entity)).collect();
+        (transforms, simulation.get_record().clone())
+    }
+
+    #[test]
+    fn same_seed_and_throws_give_identical_transforms() {
+        let (first, first_record) = run(Simulation::new(7), 300);
+        let (second, second_record) = run(Simulation::new(7), 300);
+        assert_eq!(first_record, second_record);
+        assert_eq!(first, second);
+        let (other, _) = run(Simulation::new(8), 300);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn replaying_a_record_gives_identical_transforms() {
+        let (recorded, record) = run(Simulation::new(7), 300);
+        let (replayed, _) = run(Simulation::replay(record), 300);
+        assert_eq!(recorded, replayed);
+    }
+
+    #[test]
+    fn records_survive_saving_and_loading() {
+        let (_, record) = run(Simulation::new(7), 60);
+        let json_file_path = std::env::temp_dir().join(format!("tstp_record_{}.json", std::process::id()));
+        let json_file_path = json_file_path.to_str().unwrap();
+        record.save(json_file_path);
+        let loaded = RollRecord::load(json_file_path);
+        std::fs::remove_file(json_file_path).unwrap();
+        assert_eq!(loaded, Ok(record));
+    }
+
+    #[test]
+    fn loading_a_bad_record_reports_the_problem() {
+        let json_file_path = std::env::temp_dir().join(format!("tstp_bad_record_{}.json", std::process::id()));
+        let json_file_path = json_file_path.to_str().unwrap();
+        std::fs::write(json_file_path, r#"{ "seed": "7", "throws": [{ "tick": 0, "body": 1, "impulse": [1.0, 0.0] }] }"#).unwrap();
+        let loaded = RollRecord::load(json_file_path);
+        std::fs::remove_file(json_file_path).unwrap();
+        assert!(loaded.unwrap_err().contains("impulse"));
+        assert!(RollRecord::load("no/such/record.json").is_err());
+    }
+
+    #[test]
+    fn entities_without_rigid_bodies_are_not_thrown() {
+        let (mut entities, _, _) = table();
+        let walker = entities.get_agents().iter().map(|(entity, _)| entity).next().unwrap();
+        let mut simulation = Simulation::new(7);
+        simulation.throw(&mut entities, walker, [1.0, 0.0, 0.0], [0.0, 0.0, 0.0]);
+        assert!(simulation.get_record().get_throws().is_empty());
+    }
+
+    #[test]
+    fn agents_blocked_by_an_obstacle_replan_round_it_a_few_per_tick() {
+        let (_, mut nav_mesh, physics) = table();
+        let mut entities = walkers(&nav_mesh);
+        let mut simulation = Simulation::new(7);
+        simulation.step(&mut entities, &mut nav_mesh, &physics, TIME_STEP);
+        assert!(simulation.replans.is_empty());
+        // a wall across the middle of the table blocks every path
+        let wall = Obstacle::new([0.0, 0.0, 0.0], ObstacleShape::Box { half_extents: [0.05, 0.7], angle: 0.0 });
+        nav_mesh.add_obstacle(wall);
+        simulation.step(&mut entities, &mut nav_mesh, &physics, TIME_STEP);
+        assert_eq!(simulation.replans.len(), 6 - REPLANS_PER_TICK);
+        simulation.step(&mut entities, &mut nav_mesh, &physics, TIME_STEP);
+        assert!(simulation.replans.is_empty());
+        // every new path detours past an end of the wall
+        for (entity, agent) in entities.get_agents().iter() {
+            assert_eq!(agent.get_nav_status(), NavStatus::Moving);
+            assert!(!agent.path_crosses(entities.get_transform(entity), &[wall.footprint(0.0)]),
+                "path {:?} crosses the wall", agent.get_steering().get_path());
+        }
+    }
+
+    #[test]
+    fn agents_waiting_to_replan_are_not_stuck() {
+        let (_, mut nav_mesh, physics) = table();
+        let mut entities = walkers(&nav_mesh);
+        let mut simulation = Simulation::new(7);
+        simulation.set_replans_per_tick(0);
+        // a wall right across the table, so walking on only presses them against it
+        nav_mesh.add_obstacle(Obstacle::new([0.0, 0.0, 0.0], ObstacleShape::Box { half_extents: [0.05, 1.0], angle: 0.0 }));
+        // long enough to walk into the carved edge and stand there well past the stuck time
+        for _ in 0..300 {
+            simulation.step(&mut entities, &mut nav_mesh, &physics, TIME_STEP);
+        }
+        assert_eq!(simulation.replans.len(), 6);
+        assert!(entities.get_agents().iter().all(|(_, agent)| agent.get_nav_status() == NavStatus::Moving));
+    }
+}
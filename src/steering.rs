@@ -0,0 +1,485 @@
+use cgmath::{
+    InnerSpace,
+    Vector3,
+    Zero
+};
+use rand::Rng;
+
+use crate::{
+    avoidance::Avoidance,
+    entity::Entity,
+    path::ArcPath
+};
+
+/// Entity, position, velocity and size of a steered agent as seen by the others, avoiding agents
+/// take their share of getting out of each other's way
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SteeringAgent {
+    pub entity: Entity,
+    pub position: Vector3<f32>,
+    pub velocity: Vector3<f32>,
+    pub radius: f32,
+    pub avoiding: bool
+}
+
+/// Per agent limits on movement, turn rate is in radians per second
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SteeringLimits {
+    max_speed: f32,
+    max_acceleration: f32,
+    max_turn_rate: f32
+}
+#[allow(dead_code)]
+impl SteeringLimits {
+    pub fn new(max_speed: f32, max_acceleration: f32, max_turn_rate: f32) -> SteeringLimits {
+        SteeringLimits {
+            max_speed,
+            max_acceleration,
+            max_turn_rate
+        }
+    }
+    pub fn get_max_speed(&self) -> f32 {
+        self.max_speed
+    }
+    pub fn get_max_acceleration(&self) -> f32 {
+        self.max_acceleration
+    }
+    pub fn get_max_turn_rate(&self) -> f32 {
+        self.max_turn_rate
+    }
+}
+
+/// Rule producing an acceleration, quarries are indices into the agents passed to Steering::calculate
+/// and neighbourhood rules only consider agents within their radius
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Behaviour {
+    Seek { target: [f32; 3] },
+    Arrive { target: [f32; 3], slowing_radius: f32 },
+    Flee { target: [f32; 3], panic_radius: f32 },
+    Pursue { quarry: Entity },
+    Evade { quarry: Entity, panic_radius: f32 },
+    Wander { distance: f32, radius: f32, jitter: f32 },
+    FollowPath { waypoint_radius: f32, slowing_radius: f32 },
+    Separation { radius: f32 },
+    Cohesion { radius: f32 },
+    Alignment { radius: f32 }
+}
+
+/// Weighted blend of behaviours along with the state they keep between ticks
+#[derive(Debug, Clone)]
+pub struct Steering {
+    behaviours: Vec<(Behaviour, f32)>,
+    limits: SteeringLimits,
+    path: Vec<Vector3<f32>>,
+    reached: Vec<Vector3<f32>>,
+    curve: Option<ArcPath>,
+    waypoint_distances: Vec<f32>,
+    travelled: f32,
+    wander_angle: f32,
+    avoidance: Option<Avoidance>
+}
+#[allow(dead_code)]
+impl Steering {
+    pub fn new(limits: SteeringLimits) -> Steering {
+        Steering {
+            behaviours: Vec::new(),
+            limits,
+            path: Vec::new(),
+            reached: Vec::new(),
+            curve: None,
+            waypoint_distances: Vec::new(),
+            travelled: 0.0,
+            wander_angle: 0.0,
+            avoidance: None
+        }
+    }
+    pub fn add_behaviour(&mut self, behaviour: Behaviour, weight: f32) {
+        self.behaviours.push((behaviour, weight));
+    }
+    pub fn clear_behaviours(&mut self) {
+        self.behaviours.clear();
+    }
+    pub fn get_behaviours(&self) -> &[(Behaviour, f32)] {
+        &self.behaviours
+    }
+    pub fn get_limits(&self) -> SteeringLimits {
+        self.limits
+    }
+    pub fn set_limits(&mut self, limits: SteeringLimits) {
+        self.limits = limits;
+    }
+    pub fn get_avoidance(&self) -> Option<Avoidance> {
+        self.avoidance
+    }
+    /// Turns avoidance of other agents on or off, the blended behaviours then give the preferred velocity
+    /// takes   avoidance settings as Option<Avoidance>
+    pub fn set_avoidance(&mut self, avoidance: Option<Avoidance>) {
+        self.avoidance = avoidance;
+    }
+    /// Replaces the path followed by FollowPath, followed waypoint to waypoint until given a curve
+    /// takes   waypoints in order as Vec<[f32; 3]>
+    pub fn set_path(&mut self, path: Vec<[f32; 3]>) {
+        self.path = path.into_iter().map(Vector3::from).collect();
+        self.reached.clear();
+        self.curve = None;
+    }
+    /// Has FollowPath follow a curve through or near the path's waypoints by distance along it,
+    /// steering for the point the waypoint radius ahead, waypoints are reached as the curve passes them
+    /// takes   curve as ArcPath
+    pub fn set_curve(&mut self, curve: ArcPath) {
+        let mut distance = 0.0;
+        self.waypoint_distances = self.path.iter()
+            .map(|waypoint| {
+                distance = curve.project(*waypoint, distance, curve.get_length());
+                distance
+            })
+            .collect();
+        self.travelled = 0.0;
+        self.curve = Some(curve);
+    }
+    pub fn get_curve(&self) -> Option<&ArcPath> {
+        self.curve.as_ref()
+    }
+    pub fn clear_path(&mut self) {
+        self.path.clear();
+        self.curve = None;
+    }
+    pub fn get_path(&self) -> Vec<[f32; 3]> {
+        self.path.iter().map(|point| (*point).into()).collect()
+    }
+    /// Takes the waypoints FollowPath dropped since the last call, the last waypoint is never dropped
+    /// returns reached waypoints in order as Vec<[f32; 3]>
+    pub fn take_reached(&mut self) -> Vec<[f32; 3]> {
+        self.reached.drain(..).map(|point| point.into()).collect()
+    }
+    /// Blends the behaviours by weight
+    /// takes   every agent, the steered one included, as [SteeringAgent]
+    ///         index of the steered agent as usize
+    ///         random number generator for wandering as Rng
+    /// returns acceleration, at most the acceleration limit, as Vector3<f32>
+    pub fn calculate<R: Rng>(&mut self, agents: &[SteeringAgent], index: usize, rng: &mut R) -> Vector3<f32> {
+        let agent = agents[index];
+        let max_speed = self.limits.max_speed;
+        let mut acceleration = Vector3::zero();
+        for (behaviour, weight) in self.behaviours.clone() {
+            let steering = match behaviour {
+                Behaviour::Seek { target } => seek(&agent, target.into(), max_speed),
+                Behaviour::Arrive { target, slowing_radius } => arrive(&agent, target.into(), slowing_radius, max_speed),
+                Behaviour::Flee { target, panic_radius } => flee(&agent, target.into(), panic_radius, max_speed),
+                // a quarry missing from the agents, without a transform or agent, is not chased or fled
+                Behaviour::Pursue { quarry } => match find_quarry(agents, quarry) {
+                    Some(quarry) => pursue(&agent, quarry, max_speed),
+                    None => continue
+                },
+                Behaviour::Evade { quarry, panic_radius } => match find_quarry(agents, quarry) {
+                    Some(quarry) => evade(&agent, quarry, panic_radius, max_speed),
+                    None => continue
+                },
+                Behaviour::Wander { distance, radius, jitter } => {
+                    self.wander_angle += (rng.gen::<f32>() * 2.0 - 1.0) * jitter;
+                    wander(&agent, self.wander_angle, distance, radius, max_speed)
+                },
+                Behaviour::FollowPath { waypoint_radius, slowing_radius } => self.follow_path(&agent, waypoint_radius, slowing_radius),
+                Behaviour::Separation { radius } => separation(&agent, &neighbours(agents, index, radius), radius, max_speed),
+                Behaviour::Cohesion { radius } => cohesion(&agent, &neighbours(agents, index, radius), max_speed),
+                Behaviour::Alignment { radius } => alignment(&agent, &neighbours(agents, index, radius))
+            };
+            acceleration += steering * weight;
+        }
+        truncate(acceleration, self.limits.max_acceleration)
+    }
+    /// Moves along the path, dropping waypoints once within their radius and arriving at the last
+    fn follow_path(&mut self, agent: &SteeringAgent, waypoint_radius: f32, slowing_radius: f32) -> Vector3<f32> {
+        if let Some(curve) = self.curve.as_ref() {
+            // only ever moves forwards, looking no further ahead than the agent could have got
+            self.travelled = curve.project(agent.position, self.travelled, self.travelled + waypoint_radius * 2.0);
+            while self.path.len() > 1 && self.waypoint_distances[0] <= self.travelled + waypoint_radius {
+                self.reached.push(self.path.remove(0));
+                self.waypoint_distances.remove(0);
+            }
+            // an agent pushed off the end still has its distance from it to cover
+            let end = curve.point_at(curve.get_length());
+            let remaining = (curve.get_length() - self.travelled).max((end - agent.position).magnitude());
+            let speed = if remaining < slowing_radius { self.limits.max_speed * remaining / slowing_radius } else { self.limits.max_speed };
+            return direction(curve.point_at(self.travelled + waypoint_radius) - agent.position) * speed - agent.velocity;
+        }
+        while self.path.len() > 1 && (self.path[0] - agent.position).magnitude() <= waypoint_radius {
+            self.reached.push(self.path.remove(0));
+        }
+        match self.path.len() {
+            0 => -agent.velocity,
+            1 => arrive(agent, self.path[0], slowing_radius, self.limits.max_speed),
+            _ => seek(agent, self.path[0], self.limits.max_speed)
+        }
+    }
+    /// Accelerates a velocity within the speed and turn rate limits
+    /// takes   current velocity as Vector3<f32>
+    ///         acceleration as Vector3<f32>
+    ///         time step in seconds as f32
+    /// returns new velocity as Vector3<f32>
+    pub fn apply(&self, velocity: Vector3<f32>, acceleration: Vector3<f32>, time_step: f32) -> Vector3<f32> {
+        let target = truncate(velocity + acceleration * time_step, self.limits.max_speed);
+        let (speed, target_speed) = (velocity.magnitude(), target.magnitude());
+        if speed <= f32::EPSILON || target_speed <= f32::EPSILON {
+            return target;
+        }
+        let (heading, target_heading) = (velocity / speed, target / target_speed);
+        let angle = heading.dot(target_heading).clamp(-1.0, 1.0).acos();
+        let max_angle = self.limits.max_turn_rate * time_step;
+        if angle <= max_angle {
+            return target;
+        }
+        // turn the heading towards the target heading by the largest angle allowed
+        let turned = if angle >= std::f32::consts::PI - 1.0e-4 {
+            let side = if heading.x.abs() < 0.9 { Vector3::unit_x() } else { Vector3::unit_y() };
+            let axis = heading.cross(side).normalize();
+            heading * max_angle.cos() + axis.cross(heading) * max_angle.sin()
+        } else {
+            (heading * (angle - max_angle).sin() + target_heading * max_angle.sin()) / angle.sin()
+        };
+        // only the part of the target velocity along the turned heading is kept, so turning sharply brakes
+        // rather than carrying full speed round the corner and swinging wide of the path
+        turned * target.dot(turned).max(0.0)
+    }
+}
+
+/// Steers straight towards a point at full speed
+/// takes   agent as SteeringAgent
+///         target as Vector3<f32>
+///         maximum speed as f32
+/// returns acceleration as Vector3<f32>
+pub fn seek(agent: &SteeringAgent, target: Vector3<f32>, max_speed: f32) -> Vector3<f32> {
+    direction(target - agent.position) * max_speed - agent.velocity
+}
+
+/// Steers towards a point, slowing down inside the slowing radius to stop on it
+/// takes   agent as SteeringAgent
+///         target as Vector3<f32>
+///         slowing radius as f32
+///         maximum speed as f32
+/// returns acceleration as Vector3<f32>
+pub fn arrive(agent: &SteeringAgent, target: Vector3<f32>, slowing_radius: f32, max_speed: f32) -> Vector3<f32> {
+    let offset = target - agent.position;
+    let distance = offset.magnitude();
+    let speed = if distance < slowing_radius { max_speed * distance / slowing_radius } else { max_speed };
+    direction(offset) * speed - agent.velocity
+}
+
+/// Steers away from a point while within the panic radius
+/// takes   agent as SteeringAgent
+///         point to flee as Vector3<f32>
+///         panic radius as f32
+///         maximum speed as f32
+/// returns acceleration as Vector3<f32>
+pub fn flee(agent: &SteeringAgent, target: Vector3<f32>, panic_radius: f32, max_speed: f32) -> Vector3<f32> {
+    let offset = agent.position - target;
+    if offset.magnitude() > panic_radius {
+        return Vector3::zero();
+    }
+    direction(offset) * max_speed - agent.velocity
+}
+
+/// Seeks where a moving quarry will be, looking further ahead the further away it is
+/// takes   agent as SteeringAgent
+///         quarry as SteeringAgent
+///         maximum speed as f32
+/// returns acceleration as Vector3<f32>
+pub fn pursue(agent: &SteeringAgent, quarry: &SteeringAgent, max_speed: f32) -> Vector3<f32> {
+    seek(agent, predict(agent, quarry, max_speed), max_speed)
+}
+
+/// Flees where a moving pursuer will be
+/// takes   agent as SteeringAgent
+///         pursuer as SteeringAgent
+///         panic radius as f32
+///         maximum speed as f32
+/// returns acceleration as Vector3<f32>
+pub fn evade(agent: &SteeringAgent, pursuer: &SteeringAgent, panic_radius: f32, max_speed: f32) -> Vector3<f32> {
+    flee(agent, predict(agent, pursuer, max_speed), panic_radius, max_speed)
+}
+
+/// Seeks a point on a circle projected ahead of the agent, the circle lies in the xy plane of the table
+/// takes   agent as SteeringAgent
+///         angle of the point around the circle as f32
+///         distance of the circle ahead as f32
+///         circle radius as f32
+///         maximum speed as f32
+/// returns acceleration as Vector3<f32>
+pub fn wander(agent: &SteeringAgent, angle: f32, distance: f32, radius: f32, max_speed: f32) -> Vector3<f32> {
+    let heading = if agent.velocity.magnitude2() > f32::EPSILON { agent.velocity.normalize() } else { Vector3::unit_x() };
+    let target = agent.position + heading * distance + Vector3::new(angle.cos(), angle.sin(), 0.0) * radius;
+    seek(agent, target, max_speed)
+}
+
+/// Steers away from neighbours, harder the closer they are
+/// takes   agent as SteeringAgent
+///         neighbours as [SteeringAgent]
+///         radius neighbours are considered within as f32
+///         maximum speed as f32
+/// returns acceleration as Vector3<f32>
+pub fn separation(agent: &SteeringAgent, neighbours: &[SteeringAgent], radius: f32, max_speed: f32) -> Vector3<f32> {
+    neighbours.iter()
+        .map(|neighbour| {
+            let offset = agent.position - neighbour.position;
+            direction(offset) * (1.0 - offset.magnitude() / radius).max(0.0) * max_speed
+        })
+        .fold(Vector3::zero(), |total, push| total + push)
+}
+
+/// Seeks the centre of the neighbours
+/// takes   agent as SteeringAgent
+///         neighbours as [SteeringAgent]
+///         maximum speed as f32
+/// returns acceleration as Vector3<f32>
+pub fn cohesion(agent: &SteeringAgent, neighbours: &[SteeringAgent], max_speed: f32) -> Vector3<f32> {
+    if neighbours.is_empty() {
+        return Vector3::zero();
+    }
+    let centre = neighbours.iter().fold(Vector3::zero(), |total, neighbour| total + neighbour.position) / neighbours.len() as f32;
+    seek(agent, centre, max_speed)
+}
+
+/// Matches the average velocity of the neighbours
+/// takes   agent as SteeringAgent
+///         neighbours as [SteeringAgent]
+/// returns acceleration as Vector3<f32>
+pub fn alignment(agent: &SteeringAgent, neighbours: &[SteeringAgent]) -> Vector3<f32> {
+    if neighbours.is_empty() {
+        return Vector3::zero();
+    }
+    let velocity = neighbours.iter().fold(Vector3::zero(), |total, neighbour| total + neighbour.velocity) / neighbours.len() as f32;
+    velocity - agent.velocity
+}
+
+fn neighbours(agents: &[SteeringAgent], index: usize, radius: f32) -> Vec<SteeringAgent> {
+    agents.iter()
+        .enumerate()
+        .filter(|(other, agent)| *other != index && (agent.position - agents[index].position).magnitude() < radius)
+        .map(|(_, agent)| *agent)
+        .collect()
+}
+
+fn find_quarry(agents: &[SteeringAgent], quarry: Entity) -> Option<&SteeringAgent> {
+    agents.iter().find(|agent| agent.entity == quarry)
+}
+
+fn predict(agent: &SteeringAgent, other: &SteeringAgent, max_speed: f32) -> Vector3<f32> {
+    let look_ahead = (other.position - agent.position).magnitude() / (max_speed + agent.velocity.magnitude()).max(f32::EPSILON);
+    other.position + other.velocity * look_ahead
+}
+
+fn direction(vector: Vector3<f32>) -> Vector3<f32> {
+    if vector.magnitude2() > f32::EPSILON {
+        vector.normalize()
+    } else {
+        Vector3::zero()
+    }
+}
+
+fn truncate(vector: Vector3<f32>, length: f32) -> Vector3<f32> {
+    if vector.magnitude() > length {
+        vector.normalize() * length
+    } else {
+        vector
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{
+        rngs::StdRng,
+        SeedableRng
+    };
+
+    fn standing(position: [f32; 3]) -> SteeringAgent {
+        SteeringAgent {
+            entity: Entity::new(0),
+            position: position.into(),
+            velocity: Vector3::zero(),
+            radius: 0.1,
+            avoiding: true
+        }
+    }
+
+    fn angle(a: Vector3<f32>, b: Vector3<f32>) -> f32 {
+        a.normalize().dot(b.normalize()).clamp(-1.0, 1.0).acos()
+    }
+
+    #[test]
+    fn arrive_slows_down_inside_its_radius() {
+        let agent = standing([0.0, 0.0, 0.0]);
+        let far = arrive(&agent, Vector3::new(3.0, 0.0, 0.0), 1.0, 2.0);
+        let near = arrive(&agent, Vector3::new(0.5, 0.0, 0.0), 1.0, 2.0);
+        let there = arrive(&agent, Vector3::zero(), 1.0, 2.0);
+        assert!((far - Vector3::new(2.0, 0.0, 0.0)).magnitude() < 1.0e-6);
+        assert!((near - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1.0e-6);
+        assert_eq!(there, Vector3::zero());
+    }
+
+    #[test]
+    fn flee_and_evade_steer_away_within_the_panic_radius() {
+        let agent = standing([0.0, 0.0, 0.0]);
+        let away = flee(&agent, Vector3::new(1.0, 0.0, 0.0), 2.0, 1.0);
+        assert!(away.x < 0.0 && away.y.abs() < 1.0e-6);
+        assert_eq!(flee(&agent, Vector3::new(3.0, 0.0, 0.0), 2.0, 1.0), Vector3::zero());
+        // the pursuer passes above heading along x, so evading also backs away from where it is going
+        let pursuer = SteeringAgent {
+            entity: Entity::new(1),
+            position: Vector3::new(0.0, 1.0, 0.0),
+            velocity: Vector3::new(2.0, 0.0, 0.0),
+            radius: 0.1,
+            avoiding: true
+        };
+        let away = evade(&agent, &pursuer, 5.0, 1.0);
+        assert!(away.x < 0.0 && away.y < 0.0);
+        let towards = pursue(&agent, &pursuer, 1.0);
+        assert!(towards.x > 0.0 && towards.y > 0.0);
+    }
+
+    #[test]
+    fn behaviours_blend_by_weight() {
+        let mut steering = Steering::new(SteeringLimits::new(1.0, 10.0, std::f32::consts::PI));
+        steering.add_behaviour(Behaviour::Seek { target: [5.0, 0.0, 0.0] }, 1.0);
+        steering.add_behaviour(Behaviour::Seek { target: [0.0, 5.0, 0.0] }, 0.5);
+        let acceleration = steering.calculate(&[standing([0.0, 0.0, 0.0])], 0, &mut StdRng::seed_from_u64(0));
+        assert!((acceleration - Vector3::new(1.0, 0.5, 0.0)).magnitude() < 1.0e-6);
+        // the blend is cut down to the acceleration limit
+        steering.set_limits(SteeringLimits::new(1.0, 0.5, std::f32::consts::PI));
+        let acceleration = steering.calculate(&[standing([0.0, 0.0, 0.0])], 0, &mut StdRng::seed_from_u64(0));
+        assert!((acceleration.magnitude() - 0.5).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn pursuing_finds_the_quarry_by_entity() {
+        let mut steering = Steering::new(SteeringLimits::new(1.0, 10.0, std::f32::consts::PI));
+        steering.add_behaviour(Behaviour::Pursue { quarry: Entity::new(7) }, 1.0);
+        let quarry = SteeringAgent {
+            entity: Entity::new(7),
+            ..standing([0.0, 2.0, 0.0])
+        };
+        let acceleration = steering.calculate(&[standing([0.0, 0.0, 0.0]), quarry], 0, &mut StdRng::seed_from_u64(0));
+        assert!((acceleration - Vector3::new(0.0, 1.0, 0.0)).magnitude() < 1.0e-6);
+        // a quarry gone from the agents is no longer chased
+        let acceleration = steering.calculate(&[standing([0.0, 0.0, 0.0])], 0, &mut StdRng::seed_from_u64(0));
+        assert_eq!(acceleration, Vector3::zero());
+    }
+
+    #[test]
+    fn apply_turns_at_most_the_turn_rate_per_tick() {
+        let steering = Steering::new(SteeringLimits::new(1.0, 100.0, std::f32::consts::FRAC_PI_2));
+        let velocity = Vector3::new(1.0, 0.0, 0.0);
+        let time_step = 0.1;
+        let max_angle = std::f32::consts::FRAC_PI_2 * time_step;
+        let sideways = steering.apply(velocity, Vector3::new(-10.0, 10.0, 0.0), time_step);
+        assert!((angle(velocity, sideways) - max_angle).abs() < 1.0e-4);
+        assert!(sideways.y > 0.0);
+        // turning back on itself brakes rather than swinging round
+        let reversed = steering.apply(velocity, Vector3::new(-20.0, 0.0, 0.0), time_step);
+        assert!(reversed.magnitude() < 1.0e-6);
+        // small turns go through untouched
+        let gentle = steering.apply(velocity, Vector3::new(0.0, 0.5, 0.0), time_step);
+        assert!((gentle - Vector3::new(1.0, 0.05, 0.0).normalize()).magnitude() < 1.0e-6);
+    }
+}
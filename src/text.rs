@@ -0,0 +1,241 @@
+use glium::{
+    implement_vertex,
+    Surface,
+    uniform,
+    uniforms::{
+        MagnifySamplerFilter,
+        MinifySamplerFilter
+    }
+};
+use std::collections::HashMap;
+
+use crate::etc::build_program;
+
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+const GLYPH_PADDING: u32 = 1;
+const ATLAS_COLUMNS: u32 = 16;
+const SOLID_GLYPH: char = '\u{2588}';
+
+// 5x7 glyph rows from top to bottom, leftmost pixel in bit 4
+const FONT_GLYPHS: &[(char, [u8; 7])] = &[
+    (' ', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]),
+    ('0', [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E]),
+    ('1', [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('2', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F]),
+    ('3', [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E]),
+    ('4', [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02]),
+    ('5', [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E]),
+    ('6', [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E]),
+    ('7', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E]),
+    ('9', [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C]),
+    ('A', [0x0E, 0x11, 0x11, 0x11, 0x1F, 0x11, 0x11]),
+    ('B', [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E]),
+    ('C', [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E]),
+    ('D', [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C]),
+    ('E', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F]),
+    ('F', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10]),
+    ('G', [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F]),
+    ('H', [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('I', [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F]),
+    ('M', [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('P', [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10]),
+    ('Q', [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D]),
+    ('R', [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11]),
+    ('S', [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E]),
+    ('T', [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A]),
+    ('X', [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04]),
+    ('Z', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C]),
+    (',', [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08]),
+    (':', [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00]),
+    (';', [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x04, 0x08]),
+    ('-', [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00]),
+    ('+', [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00]),
+    ('/', [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00]),
+    ('(', [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02]),
+    (')', [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08]),
+    ('[', [0x0E, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0E]),
+    (']', [0x0E, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0E]),
+    ('<', [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02]),
+    ('>', [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08]),
+    ('=', [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00]),
+    ('%', [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03]),
+    ('!', [0x04, 0x04, 0x04, 0x04, 0x00, 0x00, 0x04]),
+    ('?', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04]),
+    ('_', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F]),
+    ('#', [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A]),
+    ('*', [0x00, 0x04, 0x15, 0x0E, 0x15, 0x04, 0x00]),
+    ('|', [0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('\'', [0x04, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00]),
+    ('"', [0x0A, 0x0A, 0x00, 0x00, 0x00, 0x00, 0x00]),
+    ('^', [0x04, 0x0A, 0x11, 0x00, 0x00, 0x00, 0x00]),
+    ('@', [0x0E, 0x11, 0x17, 0x15, 0x17, 0x10, 0x0E]),
+    ('&', [0x0C, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0D]),
+    (SOLID_GLYPH, [0x1F, 0x1F, 0x1F, 0x1F, 0x1F, 0x1F, 0x1F])
+];
+
+#[derive(Debug, Copy, Clone)]
+pub struct TextVertex {
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+    color: [f32; 4]
+}
+implement_vertex!(TextVertex, position, tex_coords, color);
+
+#[derive(Debug)]
+pub struct BitmapFont {
+    texture: glium::texture::Texture2d,
+    glyphs: HashMap<char, [f32; 4]>,
+    glyph_size: [f32; 2]
+}
+impl BitmapFont {
+    /// Constructs the built-in 5x7 pixel font
+    /// takes   display as glium::Display
+    /// returns font with its glyph atlas uploaded as BitmapFont
+    pub fn builtin(display: &glium::Display) -> BitmapFont {
+        let cell_width = GLYPH_WIDTH + GLYPH_PADDING * 2;
+        let cell_height = GLYPH_HEIGHT + GLYPH_PADDING * 2;
+        let rows = (FONT_GLYPHS.len() as u32).div_ceil(ATLAS_COLUMNS);
+        let width = cell_width * ATLAS_COLUMNS;
+        let height = cell_height * rows;
+        // opengl images start at the bottom row
+        let mut pixels = vec![0u8; (width * height * 4) as usize];
+        let mut glyphs = HashMap::new();
+        for (index, (character, bitmap)) in FONT_GLYPHS.iter().enumerate() {
+            let cell_x = (index as u32 % ATLAS_COLUMNS) * cell_width + GLYPH_PADDING;
+            let cell_y = (index as u32 / ATLAS_COLUMNS) * cell_height + GLYPH_PADDING;
+            for (row, bits) in bitmap.iter().enumerate() {
+                let y = cell_y + GLYPH_HEIGHT - 1 - row as u32;
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (0x10 >> column) != 0 {
+                        let offset = (((y * width) + cell_x + column) * 4) as usize;
+                        pixels[offset..offset + 4].copy_from_slice(&[255, 255, 255, 255]);
+                    }
+                }
+            }
+            glyphs.insert(*character, [
+                cell_x as f32 / width as f32,
+                cell_y as f32 / height as f32,
+                (cell_x + GLYPH_WIDTH) as f32 / width as f32,
+                (cell_y + GLYPH_HEIGHT) as f32 / height as f32
+            ]);
+        }
+        let raw_image = glium::texture::RawImage2d::from_raw_rgba(pixels, (width, height));
+        BitmapFont {
+            texture: glium::texture::Texture2d::new(display, raw_image).unwrap(),
+            glyphs,
+            glyph_size: [GLYPH_WIDTH as f32, GLYPH_HEIGHT as f32]
+        }
+    }
+    pub fn get_glyph_size(&self) -> [f32; 2] {
+        self.glyph_size
+    }
+    fn get_glyph(&self, character: char) -> [f32; 4] {
+        match self.glyphs.get(&character.to_ascii_uppercase()) {
+            Some(rect) => *rect,
+            None => self.glyphs[&'?']
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TextRenderer {
+    font: BitmapFont,
+    program: glium::Program,
+    vertices: Vec<TextVertex>
+}
+#[allow(dead_code)]
+impl TextRenderer {
+    pub fn new(display: &glium::Display, font: BitmapFont, vertex_shader_file_path: &str, fragment_shader_file_path: &str) -> TextRenderer {
+        TextRenderer {
+            font,
+            program: build_program(display, vertex_shader_file_path, fragment_shader_file_path),
+            vertices: Vec::new()
+        }
+    }
+    /// Measures a single line of text
+    /// takes   text as str
+    ///         pixel scale of the glyphs as f32
+    /// returns width and height in pixels as [f32; 2]
+    pub fn text_size(&self, text: &str, scale: f32) -> [f32; 2] {
+        let [glyph_width, glyph_height] = self.font.get_glyph_size();
+        let count = text.chars().count() as f32;
+        [(count * (glyph_width + 1.0) - 1.0).max(0.0) * scale, glyph_height * scale]
+    }
+    /// Queues a line of text for drawing
+    /// takes   text as str
+    ///         top left corner in window pixels as [f32; 2]
+    ///         pixel scale of the glyphs as f32
+    ///         color as [f32; 4]
+    pub fn queue_text(&mut self, text: &str, position: [f32; 2], scale: f32, color: [f32; 4]) {
+        let [glyph_width, glyph_height] = self.font.get_glyph_size();
+        let mut x = position[0];
+        for character in text.chars() {
+            if character != ' ' {
+                let rect = self.font.get_glyph(character);
+                self.push_quad([x, position[1]], [glyph_width * scale, glyph_height * scale], rect, color);
+            }
+            x += (glyph_width + 1.0) * scale;
+        }
+    }
+    /// Queues a solid rectangle for drawing
+    /// takes   top left corner in window pixels as [f32; 2]
+    ///         width and height in pixels as [f32; 2]
+    ///         color as [f32; 4]
+    pub fn queue_rect(&mut self, position: [f32; 2], size: [f32; 2], color: [f32; 4]) {
+        let rect = self.font.get_glyph(SOLID_GLYPH);
+        // sample the middle of the solid glyph so filtering never reaches its border
+        let u = (rect[0] + rect[2]) / 2.0;
+        let v = (rect[1] + rect[3]) / 2.0;
+        self.push_quad(position, size, [u, v, u, v], color);
+    }
+    fn push_quad(&mut self, position: [f32; 2], size: [f32; 2], rect: [f32; 4], color: [f32; 4]) {
+        let [x0, y0] = position;
+        let x1 = x0 + size[0];
+        let y1 = y0 + size[1];
+        let top_left = TextVertex { position: [x0, y0], tex_coords: [rect[0], rect[3]], color };
+        let top_right = TextVertex { position: [x1, y0], tex_coords: [rect[2], rect[3]], color };
+        let bottom_left = TextVertex { position: [x0, y1], tex_coords: [rect[0], rect[1]], color };
+        let bottom_right = TextVertex { position: [x1, y1], tex_coords: [rect[2], rect[1]], color };
+        self.vertices.extend_from_slice(&[top_left, bottom_left, top_right, top_right, bottom_left, bottom_right]);
+    }
+    /// Draws and clears everything queued since the last call
+    /// takes   display as glium::Display
+    ///         drawing surface as glium::Frame
+    /// returns number of draw calls issued as u32
+    pub fn draw(&mut self, display: &glium::Display, target: &mut glium::Frame) -> u32 {
+        if self.vertices.is_empty() {
+            return 0;
+        }
+        let (width, height) = target.get_dimensions();
+        let vertices = glium::VertexBuffer::new(display, &self.vertices).unwrap();
+        let indices = glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList);
+        let params = glium::DrawParameters {
+            blend: glium::Blend::alpha_blending(),
+            .. Default::default()
+        };
+        target.draw(&vertices,
+            indices,
+            &self.program,
+            &uniform!{
+                u_screen: [width as f32, height as f32],
+                font_tex: self.font.texture.sampled()
+                    .magnify_filter(MagnifySamplerFilter::Nearest)
+                    .minify_filter(MinifySamplerFilter::Nearest)
+            },
+            &params).unwrap();
+        self.vertices.clear();
+        1
+    }
+}
@@ -0,0 +1,193 @@
+use cgmath::{
+    ElementWise,
+    InnerSpace,
+    Matrix3,
+    Matrix4,
+    Quaternion,
+    Rotation,
+    Vector3,
+    VectorSpace
+};
+use navmesh::NavVec3;
+
+/// Position, orientation and scale of an object
+///
+/// Conventions
+/// - orientation arrays are [w, x, y, z], the order cgmath reads them in, so [0.0, 0.0, 0.0, 1.0]
+///   is a half turn about z rather than the identity
+/// - the view looks down +z with +y up, making the world left handed
+/// - object files are exported right handed, so `to_model_matrix` mirrors the mesh along its x axis
+///   before placing it, the mirror belongs to the mesh and is never part of a composed transform
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    position: Vector3<f32>,
+    orientation: Quaternion<f32>,
+    scale: Vector3<f32>
+}
+#[allow(dead_code)]
+impl Transform {
+    pub fn new(position: [f32; 3], orientation: [f32; 4], scale: [f32; 3]) -> Transform {
+        Transform {
+            position: position.into(),
+            orientation: orientation.into(),
+            scale: scale.into()
+        }
+    }
+    pub fn identity() -> Transform {
+        Transform {
+            position: Vector3::new(0.0, 0.0, 0.0),
+            orientation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            scale: Vector3::new(1.0, 1.0, 1.0)
+        }
+    }
+    pub fn from_cgmath(position: Vector3<f32>, orientation: Quaternion<f32>, scale: Vector3<f32>) -> Transform {
+        Transform {
+            position,
+            orientation,
+            scale
+        }
+    }
+    /// Constructs a transform at a point facing another, +z forward and +y up
+    /// takes   eye position as [f32; 3]
+    ///         target position as [f32; 3]
+    ///         up direction as [f32; 3]
+    /// returns unscaled transform as Transform
+    pub fn look_at(eye: [f32; 3], target: [f32; 3], up: [f32; 3]) -> Transform {
+        let eye = Vector3::from(eye);
+        let forward = (Vector3::from(target) - eye).normalize();
+        let right = Vector3::from(up).cross(forward).normalize();
+        let up = forward.cross(right);
+        Transform {
+            position: eye,
+            orientation: Matrix3::from_cols(right, up, forward).into(),
+            scale: Vector3::new(1.0, 1.0, 1.0)
+        }
+    }
+    pub fn get_position(&self) -> [f32; 3] {
+        self.position.into()
+    }
+    pub fn get_orientation(&self) -> [f32; 4] {
+        self.orientation.into()
+    }
+    pub fn get_scale(&self) -> [f32; 3] {
+        self.scale.into()
+    }
+    pub fn get_nav_position(&self) -> NavVec3 {
+        NavVec3::new(self.position.x, self.position.y, self.position.z)
+    }
+    pub fn get_cgmath_position(&self) -> Vector3<f32> {
+        self.position
+    }
+    pub fn get_cgmath_orientation(&self) -> Quaternion<f32> {
+        self.orientation
+    }
+    pub fn get_cgmath_scale(&self) -> Vector3<f32> {
+        self.scale
+    }
+    pub fn position_to(&mut self, position: [f32; 3]) {
+        self.position = position.into();
+    }
+    pub fn position_by(&mut self, translation: [f32; 3]) {
+        self.position += Vector3::from(translation);
+    }
+    pub fn orientation_to(&mut self, orientation: [f32; 4]) {
+        self.orientation = orientation.into();
+    }
+    /// Rotates the transform, applying the rotation after the current orientation
+    /// takes   rotation as [f32; 4]
+    pub fn orientation_by(&mut self, rotation: [f32; 4]) {
+        self.orientation = Quaternion::from(rotation) * self.orientation;
+    }
+    /// Turns the orientation towards another along the shorter arc, by no more than an angle
+    /// takes   orientation to turn towards as [f32; 4]
+    ///         largest angle to turn by in radians as f32
+    pub fn orientation_towards(&mut self, orientation: [f32; 4], max_angle: f32) {
+        let target = Quaternion::from(orientation);
+        let target = if self.orientation.dot(target) < 0.0 { -target } else { target };
+        let angle = 2.0 * self.orientation.dot(target).min(1.0).acos();
+        self.orientation = if angle <= max_angle {
+            target
+        } else {
+            self.orientation.slerp(target, max_angle / angle)
+        };
+    }
+    pub fn scale_to(&mut self, scale: [f32; 3]) {
+        self.scale = scale.into();
+    }
+    /// Places a child transform inside this one, exact when this scale is uniform
+    /// takes   child transform relative to this one as Transform
+    /// returns child transform in this transform's parent space as Transform
+    pub fn compose(&self, child: &Transform) -> Transform {
+        Transform {
+            position: self.transform_point(child.position.into()).into(),
+            orientation: self.orientation * child.orientation,
+            scale: self.scale.mul_element_wise(child.scale)
+        }
+    }
+    /// Constructs the transform undoing this one, exact when the scale is uniform
+    /// returns inverse transform as Transform
+    pub fn inverse(&self) -> Transform {
+        let orientation = self.orientation.invert();
+        let scale = Vector3::new(1.0 / self.scale.x, 1.0 / self.scale.y, 1.0 / self.scale.z);
+        Transform {
+            position: -orientation.rotate_vector(self.position).mul_element_wise(scale),
+            orientation,
+            scale
+        }
+    }
+    /// Moves a point from local space into the transform's parent space
+    /// takes   point as [f32; 3]
+    /// returns transformed point as [f32; 3]
+    pub fn transform_point(&self, point: [f32; 3]) -> [f32; 3] {
+        (self.position + self.orientation.rotate_vector(Vector3::from(point).mul_element_wise(self.scale))).into()
+    }
+    /// Moves a direction from local space into the transform's parent space, ignoring position
+    /// takes   vector as [f32; 3]
+    /// returns transformed vector as [f32; 3]
+    pub fn transform_vector(&self, vector: [f32; 3]) -> [f32; 3] {
+        self.orientation.rotate_vector(Vector3::from(vector).mul_element_wise(self.scale)).into()
+    }
+    /// Blends towards another transform, lerping position and scale and slerping orientation
+    /// takes   transform to blend towards as Transform
+    ///         fraction of the way to the other transform as f32
+    /// returns blended transform as Transform
+    pub fn interpolate(&self, other: &Transform, amount: f32) -> Transform {
+        // a quaternion and its negation are the same rotation, blend along the shorter arc
+        let target = if self.orientation.dot(other.orientation) < 0.0 {
+            -other.orientation
+        } else {
+            other.orientation
+        };
+        Transform {
+            position: self.position.lerp(other.position, amount),
+            orientation: self.orientation.slerp(target, amount),
+            scale: self.scale.lerp(other.scale, amount)
+        }
+    }
+    /// Constructs the matrix of the transform
+    /// returns translation * rotation * scale as Matrix4<f32>
+    pub fn to_matrix(self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.position)
+            * Matrix4::from(self.orientation)
+            * Matrix4::from_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
+    }
+    /// Constructs the matrix meshes are drawn with, mirroring right handed object files along x
+    /// returns translation * rotation * scale * mirror as Matrix4<f32>
+    pub fn to_model_matrix(self) -> Matrix4<f32> {
+        self.to_matrix() * Matrix4::from_nonuniform_scale(-1.0, 1.0, 1.0)
+    }
+}
+
+impl From<NavVec3> for Transform {
+    fn from(position: NavVec3) -> Transform {
+        let mut transform = Transform::identity();
+        transform.position = Vector3::new(position.x, position.y, position.z);
+        transform
+    }
+}
+
+impl From<Transform> for Matrix4<f32> {
+    fn from(transform: Transform) -> Matrix4<f32> {
+        transform.to_matrix()
+    }
+}
@@ -0,0 +1,304 @@
+use crate::{
+    agent::{
+        NavAgent,
+        NavStatus
+    },
+    entity::{
+        render_system,
+        Entities,
+        Entity
+    },
+    gfx::Graphic,
+    obstacle::CarvedNavMesh,
+    physics::{
+        PhysicsSettings,
+        RigidBody,
+        RollResult
+    },
+    scene::{
+        NodeId,
+        Scene
+    },
+    shape::Shape,
+    simulation::Simulation,
+    transform::Transform
+};
+
+// every die weighs, bounces and grips the same
+const DIE_MASS: f32 = 1.0;
+const DIE_RESTITUTION: f32 = 0.4;
+const DIE_FRICTION: f32 = 0.5;
+
+/// Everything simulated, the entities on a table with its navigation mesh and physics, stepped by a
+/// seeded simulation without needing a window, rendering only reads the scene it keeps up to date
+#[derive(Debug, Clone)]
+pub struct World {
+    scene: Scene,
+    table: NodeId,
+    entities: Entities,
+    nav_mesh: CarvedNavMesh,
+    physics: PhysicsSettings,
+    simulation: Simulation
+}
+#[allow(dead_code)]
+impl World {
+    /// Constructs a world with an empty table
+    /// takes   navigation mesh of the table as CarvedNavMesh
+    ///         physics settings as PhysicsSettings
+    ///         simulation to step with, seeded or replaying, as Simulation
+    /// returns world as World
+    pub fn new(nav_mesh: CarvedNavMesh, physics: PhysicsSettings, simulation: Simulation) -> World {
+        let mut scene = Scene::new();
+        let table = scene.add_node(None, Transform::identity());
+        World {
+            scene,
+            table,
+            entities: Entities::new(),
+            nav_mesh,
+            physics,
+            simulation
+        }
+    }
+    /// Places an entity on the table with only a transform, to be given components
+    /// takes   position on the table as [f32; 3]
+    ///         orientation as [f32; 4]
+    /// returns entity as Entity
+    pub fn spawn(&mut self, position: [f32; 3], orientation: [f32; 4]) -> Entity {
+        self.entities.spawn(Transform::new(position, orientation, [1.0, 1.0, 1.0]))
+    }
+    /// Places an entity on the table drawn with a graphic and steered by a navigation agent
+    /// takes   position on the table as [f32; 3]
+    ///         velocity as [f32; 3]
+    ///         orientation as [f32; 4]
+    ///         graphic as Graphic
+    /// returns entity as Entity
+    pub fn add_agent(&mut self, position: [f32; 3], velocity: [f32; 3], orientation: [f32; 4], graphic: Graphic) -> Entity {
+        let entity = self.spawn(position, orientation);
+        self.entities.insert_graphic(entity, graphic);
+        self.entities.insert_agent(entity, NavAgent::new(velocity));
+        entity
+    }
+    /// Places a die on the table, a rigid body shaped like its graphic's mesh
+    /// takes   shape of the mesh as Shape
+    ///         position on the table as [f32; 3]
+    ///         orientation as [f32; 4]
+    ///         graphic as Graphic
+    /// returns entity as Entity
+    pub fn add_die(&mut self, shape: &Shape, position: [f32; 3], orientation: [f32; 4], graphic: Graphic) -> Entity {
+        let rigid_body = RigidBody::new(shape, graphic.get_transform(), DIE_MASS, DIE_RESTITUTION, DIE_FRICTION);
+        let entity = self.spawn(position, orientation);
+        self.entities.insert_graphic(entity, graphic);
+        self.entities.insert_rigid_body(entity, rigid_body);
+        entity
+    }
+    pub fn get_scene(&self) -> &Scene {
+        &self.scene
+    }
+    pub fn get_scene_mut(&mut self) -> &mut Scene {
+        &mut self.scene
+    }
+    pub fn get_table(&self) -> NodeId {
+        self.table
+    }
+    pub fn get_entities(&self) -> &Entities {
+        &self.entities
+    }
+    pub fn get_entities_mut(&mut self) -> &mut Entities {
+        &mut self.entities
+    }
+    pub fn get_nav_mesh(&self) -> &CarvedNavMesh {
+        &self.nav_mesh
+    }
+    pub fn get_nav_mesh_mut(&mut self) -> &mut CarvedNavMesh {
+        &mut self.nav_mesh
+    }
+    pub fn get_physics(&self) -> &PhysicsSettings {
+        &self.physics
+    }
+    pub fn get_simulation(&self) -> &Simulation {
+        &self.simulation
+    }
+    pub fn get_simulation_mut(&mut self) -> &mut Simulation {
+        &mut self.simulation
+    }
+    /// Pushes an entity before the next tick, recorded for replays
+    /// takes   entity with a rigid body as Entity
+    ///         impulse as [f32; 3]
+    ///         point the impulse acts at as [f32; 3]
+    pub fn throw(&mut self, entity: Entity, impulse: [f32; 3], point: [f32; 3]) {
+        self.simulation.throw(&mut self.entities, entity, impulse, point);
+    }
+    /// Throws an entity with a random impulse from the simulation's generator
+    /// takes   entity with a rigid body as Entity
+    pub fn random_throw(&mut self, entity: Entity) {
+        self.simulation.random_throw(&mut self.entities, entity);
+    }
+    /// Reads what every die rolled
+    /// returns each entity with a rigid body that has face values and its roll as Vec<(Entity, RollResult)>
+    pub fn get_rolls(&self) -> Vec<(Entity, RollResult)> {
+        self.entities.get_rigid_bodies()
+            .iter()
+            .filter_map(|(entity, _)| Some((entity, self.entities.get_roll(entity, &self.physics)?)))
+            .collect()
+    }
+    /// Tests whether nothing is left to happen without being pushed, every rigid body asleep
+    /// and no agent still making its way along a path
+    /// returns whether the world is at rest as bool
+    pub fn is_at_rest(&self) -> bool {
+        let rigid_bodies = self.entities.get_rigid_bodies();
+        rigid_bodies.iter().all(|(_, rigid_body)| rigid_body.is_resting())
+            && self.entities.get_agents().iter()
+                .filter(|(entity, _)| !rigid_bodies.contains(*entity))
+                .all(|(_, agent)| agent.get_nav_status() != NavStatus::Moving && agent.get_nav_status() != NavStatus::Stuck)
+    }
+    /// Advances the world by one tick
+    /// takes   time step in seconds as f32
+    pub fn step(&mut self, time_step: f32) {
+        self.simulation.step(&mut self.entities, &mut self.nav_mesh, &self.physics, time_step);
+    }
+    /// Advances the world by a number of ticks
+    /// takes   number of ticks as u64
+    ///         time step in seconds as f32
+    pub fn step_n(&mut self, ticks: u64, time_step: f32) {
+        for _ in 0..ticks {
+            self.step(time_step);
+        }
+    }
+    /// Steps until the world is at rest, giving up after a number of ticks
+    /// takes   most ticks to step as u64
+    ///         time step in seconds as f32
+    /// returns ticks stepped when the world came to rest as Option<u64>
+    pub fn run_until_rest(&mut self, max_ticks: u64, time_step: f32) -> Option<u64> {
+        for tick in 0..max_ticks {
+            if self.is_at_rest() {
+                return Some(tick);
+            }
+            self.step(time_step);
+        }
+        if self.is_at_rest() { Some(max_ticks) } else { None }
+    }
+    /// Runs the render system, placing the entities' scene nodes on the table where they are drawn
+    /// takes   fraction of a tick passed since the latest tick as f32
+    pub fn sync(&mut self, alpha: f32) {
+        render_system(&mut self.entities, &mut self.scene, Some(self.table), alpha);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::{
+        navigation::NavMeshLibrary,
+        scene::SceneLight,
+        shape::ShapeLibrary,
+        simulation::RollRecord
+    };
+
+    const TIME_STEP: f32 = 0.016;
+
+    // the table's navigation mesh, baked into a cache of the test's own
+    fn table_nav_mesh(test: &str) -> CarvedNavMesh {
+        let directory = std::env::temp_dir().join(format!("tstp_world_{}_{}", test, std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let mut nav_meshes = NavMeshLibrary::new();
+        nav_meshes.set_cache_directory(directory.to_str().unwrap());
+        nav_meshes.load_path("assets");
+        fs::remove_dir_all(&directory).unwrap();
+        CarvedNavMesh::new(nav_meshes.get_nav_mesh("table"), 0.1)
+    }
+
+    // the dice of the game thrown onto the table, without a window, lit like the game with the light
+    // spawned before the dice so dice entities don't start at zero
+    fn thrown_dice(test: &str, simulation: Simulation) -> World {
+        let shapes = ShapeLibrary::load("assets");
+        let mut world = World::new(table_nav_mesh(test), PhysicsSettings::table([2.0, 2.0]), simulation);
+        let light = world.spawn([0.0, 0.0, 0.0], [1.0, 0.0, 0.0, 0.0]);
+        world.get_entities_mut().insert_light(light, SceneLight::new([1.0, 1.0, 1.0]));
+        let mesh = Transform::new([0.0, 0.0, 0.0], [1.0, 0.0, 0.0, 0.0], [0.25, 0.25, 0.25]);
+        let dice = [
+            ("tetrahedron", [0.5, 0.0, -0.5]),
+            ("hexahedron", [0.25, 0.433, -0.5]),
+            ("octahedron", [-0.25, 0.433, -0.5]),
+            ("trapezohedron", [-0.5, 0.0, -0.5]),
+            ("dodecahedron", [-0.25, -0.433, -0.5]),
+            ("icosahedron", [0.25, -0.433, -0.5])
+        ];
+        for (object_key, position) in dice.iter() {
+            let die = world.add_die(shapes.get_shape(object_key), *position, [1.0, 0.0, 0.0, 0.0],
+                Graphic::new(mesh, object_key.to_string(), "texture".to_string()));
+            world.random_throw(die);
+        }
+        world
+    }
+
+    fn transforms(world: &World) -> Vec<Transform> {
+        let entities = world.get_entities();
+        entities.iter().map(|entity| *entities.get_transform(entity)).collect()
+    }
+
+    #[test]
+    fn thrown_dice_come_to_rest() {
+        let mut world = thrown_dice("rest", Simulation::new(7));
+        assert!(!world.is_at_rest());
+        let ticks = world.run_until_rest(2000, TIME_STEP).expect("dice still rolling after 2000 ticks");
+        assert_eq!(world.get_simulation().get_tick(), ticks);
+        let rolls = world.get_rolls();
+        assert_eq!(rolls.len(), 6);
+        assert!(rolls.iter().all(|(_, roll)| *roll != RollResult::Rolling), "rolls were {:?}", rolls);
+        // a world at rest stays put
+        assert_eq!(world.run_until_rest(2000, TIME_STEP), Some(0));
+    }
+
+    #[test]
+    fn step_n_matches_stepping_one_tick_at_a_time() {
+        let mut stepped = thrown_dice("step", Simulation::new(7));
+        let mut stepped_n = thrown_dice("step_n", Simulation::new(7));
+        for _ in 0..120 {
+            stepped.step(TIME_STEP);
+        }
+        stepped_n.step_n(120, TIME_STEP);
+        assert_eq!(stepped_n.get_simulation().get_tick(), 120);
+        assert_eq!(transforms(&stepped), transforms(&stepped_n));
+    }
+
+    #[test]
+    fn agents_on_their_way_keep_the_world_awake() {
+        let mut world = World::new(table_nav_mesh("walker"), PhysicsSettings::table([2.0, 2.0]), Simulation::new(7));
+        assert!(world.is_at_rest());
+        let walker = world.add_agent([-0.5, -0.5, 0.0], [0.0, 0.0, 0.0], [1.0, 0.0, 0.0, 0.0],
+            Graphic::new(Transform::identity(), "walker".to_string(), "texture".to_string()));
+        let nav_mesh = world.get_nav_mesh().get_nav_mesh().clone();
+        let (agent, transform) = world.get_entities_mut().get_agent_and_transform_mut(walker).unwrap();
+        agent.set_waypoint(&nav_mesh, transform, [0.5, 0.5, 0.0].into());
+        assert!(!world.is_at_rest());
+        assert!(world.run_until_rest(2000, TIME_STEP).is_some());
+        assert_eq!(world.get_entities().get_agent(walker).unwrap().get_nav_status(), NavStatus::Arrived);
+    }
+
+    #[test]
+    fn rolls_recorded_before_entities_replay_like_throwing_them_again() {
+        // seed 7 with the opening throws and the second die thrown again at tick 40, saved by the
+        // world of bodies this replaced, which numbered only the dice
+        let record = RollRecord::load("tests/fixtures/roll_before_entities.json").unwrap();
+        let mut replayed = thrown_dice("replayed", Simulation::replay(record.clone()));
+        replayed.run_until_rest(2000, TIME_STEP).expect("replayed dice still rolling after 2000 ticks");
+        let mut thrown = thrown_dice("thrown", Simulation::new(record.get_seed()));
+        let again = record.get_throws().iter().find(|throw| throw.tick == 40).unwrap();
+        thrown.step_n(40, TIME_STEP);
+        let die = thrown.get_entities().get_rigid_bodies().iter().nth(again.die).map(|(entity, _)| entity).unwrap();
+        thrown.throw(die, again.impulse, again.point);
+        thrown.run_until_rest(2000, TIME_STEP).expect("thrown dice still rolling after 2000 ticks");
+        assert_eq!(thrown.get_simulation().get_record(), &record);
+        assert_eq!(thrown.get_simulation().get_tick(), replayed.get_simulation().get_tick());
+        assert_eq!(transforms(&thrown), transforms(&replayed));
+        assert_eq!(thrown.get_rolls(), replayed.get_rolls());
+        // saving the record keeps the dice it throws
+        let json_file_path = std::env::temp_dir().join(format!("tstp_roll_round_trip_{}.json", std::process::id()));
+        let json_file_path = json_file_path.to_str().unwrap();
+        record.save(json_file_path);
+        let loaded = RollRecord::load(json_file_path);
+        fs::remove_file(json_file_path).unwrap();
+        assert_eq!(loaded, Ok(record));
+    }
+}